@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+
+use crate::{Instruction, Register};
+
+/// Why a program couldn't be assembled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    /// One-indexed line of the source.
+    pub line: usize,
+    pub kind: AsmErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsmErrorKind {
+    UnknownInstruction(String),
+    /// The instruction, and what it should have been given.
+    WrongOperands(&'static str, &'static str),
+    NotANumber(String),
+    UnknownRegister(String),
+    UnknownLabel(String),
+    DuplicateLabel(String),
+    /// A jump to before the start of the program, or further than just past its end.
+    JumpOutOfRange(i64),
+}
+
+/// Turns source code into instructions, one instruction per line:
+///
+/// ```text
+/// ; count down from 3
+///         addy 3
+/// loop:   addx 2      ; labels can go before an instruction, or on a line of their own
+///         addy -1
+///         jnz y loop
+/// ```
+///
+/// Comments start with `;` or `#`, and operands can be separated by spaces or commas. Jumps take a
+/// label, or an offset relative to the jump itself; jumping to just past the end of the program
+/// halts it.
+pub fn assemble(source: &str) -> Result<Vec<Instruction>, AsmError> {
+    // First pass: find the labels, and which instruction each one is in front of.
+    let mut labels: HashMap<&str, usize> = HashMap::new();
+    let mut lines = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        let mut code = line.split([';', '#']).next().unwrap_or("").trim();
+
+        while let Some((label, rest)) = code.split_once(':') {
+            let label = label.trim();
+            if label.is_empty() || label.contains(char::is_whitespace) {
+                break;
+            }
+            if labels.insert(label, lines.len()).is_some() {
+                return Err(AsmError {
+                    line: line_number,
+                    kind: AsmErrorKind::DuplicateLabel(label.to_owned()),
+                });
+            }
+            code = rest.trim();
+        }
+
+        if !code.is_empty() {
+            lines.push((line_number, code));
+        }
+    }
+
+    // Second pass: the instructions themselves, now that every label is known.
+    let length = lines.len();
+    lines
+        .iter()
+        .enumerate()
+        .map(|(address, &(line, code))| {
+            parse_instruction(code, address, length, &labels)
+                .map_err(|kind| AsmError { line, kind })
+        })
+        .collect()
+}
+
+fn parse_instruction(
+    code: &str,
+    address: usize,
+    length: usize,
+    labels: &HashMap<&str, usize>,
+) -> Result<Instruction, AsmErrorKind> {
+    use AsmErrorKind::*;
+
+    let mut words = code
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|word| !word.is_empty());
+    let mnemonic = words.next().expect("blank lines are skipped");
+    let operands: Vec<&str> = words.collect();
+
+    let offset = |operand: &str| -> Result<i64, AsmErrorKind> {
+        let offset = match labels.get(operand) {
+            Some(&target) => target as i64 - address as i64,
+            None if operand.starts_with(|c: char| c.is_alphabetic() || c == '_') => {
+                return Err(UnknownLabel(operand.to_owned()))
+            }
+            None => number(operand)?,
+        };
+        let target = address as i64 + offset;
+        if (0..=length as i64).contains(&target) {
+            Ok(offset)
+        } else {
+            Err(JumpOutOfRange(offset))
+        }
+    };
+
+    let instruction = match (mnemonic, operands.as_slice()) {
+        ("noop", []) => Instruction::Noop,
+        ("mul", &[register, factor]) => {
+            Instruction::Mul(self::register(register)?, number(factor)?)
+        }
+        ("jmp", &[target]) => Instruction::Jmp(offset(target)?),
+        ("jnz", &[register, target]) => {
+            Instruction::Jnz(self::register(register)?, offset(target)?)
+        }
+        (add, &[amount]) if add.starts_with("add") => {
+            let register =
+                self::register(&add[3..]).map_err(|_| UnknownInstruction(add.to_owned()))?;
+            Instruction::Add(register, number(amount)?)
+        }
+        ("noop", _) => return Err(WrongOperands("noop", "nothing")),
+        ("mul", _) => return Err(WrongOperands("mul", "a register and a number")),
+        ("jmp", _) => return Err(WrongOperands("jmp", "a label or an offset")),
+        ("jnz", _) => return Err(WrongOperands("jnz", "a register, and a label or an offset")),
+        (add, _) if add.starts_with("add") && self::register(&add[3..]).is_ok() => {
+            return Err(WrongOperands("add", "a number"))
+        }
+        (unknown, _) => return Err(UnknownInstruction(unknown.to_owned())),
+    };
+
+    Ok(instruction)
+}
+
+pub fn register(name: &str) -> Result<Register, AsmErrorKind> {
+    match name {
+        "x" => Ok(Register::X),
+        "y" => Ok(Register::Y),
+        "z" => Ok(Register::Z),
+        _ => Err(AsmErrorKind::UnknownRegister(name.to_owned())),
+    }
+}
+
+fn number(operand: &str) -> Result<i64, AsmErrorKind> {
+    operand
+        .parse()
+        .map_err(|_| AsmErrorKind::NotANumber(operand.to_owned()))
+}
+
+impl Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use AsmErrorKind::*;
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            UnknownInstruction(mnemonic) => write!(f, "unknown instruction {mnemonic:?}"),
+            WrongOperands(mnemonic, expected) => write!(f, "{mnemonic} takes {expected}"),
+            NotANumber(operand) => write!(f, "{operand:?} is not a number"),
+            UnknownRegister(name) => write!(f, "there's no register called {name:?}"),
+            UnknownLabel(label) => write!(f, "there's no label called {label:?}"),
+            DuplicateLabel(label) => write!(f, "{label:?} is already a label"),
+            JumpOutOfRange(offset) => write!(f, "jumping by {offset} leaves the program"),
+        }
+    }
+}
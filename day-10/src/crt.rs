@@ -0,0 +1,50 @@
+use crate::{Cpu, Observer};
+
+pub const CRT_WIDTH: usize = 40;
+pub const CRT_HEIGHT: usize = 6;
+
+/// Draws a pixel every cycle, lit if the three-pixel-wide sprite centred on X covers it.
+#[derive(Debug, Clone)]
+pub struct Crt {
+    /// Row after row, `CRT_WIDTH` pixels each. Programs that run for longer than a screenful just
+    /// keep adding rows.
+    pixels: Vec<bool>,
+}
+
+impl Crt {
+    pub fn new() -> Self {
+        Crt { pixels: Vec::new() }
+    }
+
+    pub fn pixels(&self) -> &[bool] {
+        &self.pixels
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[bool]> {
+        self.pixels.chunks(CRT_WIDTH)
+    }
+
+    /// `#` for lit pixels and `.` for dark ones, like in the puzzle.
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+        for row in self.rows() {
+            output.extend(row.iter().map(|&lit| if lit { '#' } else { '.' }));
+            output.push('\n');
+        }
+        output
+    }
+}
+
+impl Default for Crt {
+    fn default() -> Self {
+        Crt::new()
+    }
+}
+
+impl Observer for Crt {
+    fn during(&mut self, cpu: &Cpu) {
+        let column = ((cpu.cycle() - 1) % CRT_WIDTH) as i64;
+        let sprite = cpu.x() - 1..=cpu.x() + 1;
+        self.pixels.push(sprite.contains(&column));
+    }
+}
@@ -0,0 +1,221 @@
+use std::fmt::{self, Display};
+use std::io::{self, BufRead, Write};
+
+use crate::crt::{Crt, CRT_WIDTH};
+use crate::{asm, Cpu, Register};
+
+const HELP: &str = "\
+step [N]          run N cycles (1 by default)
+run               run until a breakpoint, a watched register changes, or the program halts
+break cycle N     stop just before cycle N
+break pc N        stop just before the instruction at address N starts
+watch REGISTER    stop whenever the register changes
+print             show where the CPU is up to
+crt               show what's been drawn so far
+quit
+";
+
+/// Where `run` should stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Breakpoint {
+    /// Just before this cycle.
+    Cycle(usize),
+    /// Just before the instruction at this address starts.
+    Pc(usize),
+}
+
+/// Steps through a program a cycle at a time, drawing on a CRT as it goes.
+#[derive(Debug)]
+pub struct Debugger<'a> {
+    cpu: Cpu<'a>,
+    crt: Crt,
+    breakpoints: Vec<Breakpoint>,
+    /// Registers to keep an eye on, and what they were last time we looked.
+    watches: Vec<(Register, i64)>,
+}
+
+impl<'a> Debugger<'a> {
+    pub fn new(cpu: Cpu<'a>) -> Self {
+        Debugger {
+            cpu,
+            crt: Crt::new(),
+            breakpoints: Vec::new(),
+            watches: Vec::new(),
+        }
+    }
+
+    /// Reads commands from `input` until it runs out or says `quit`.
+    pub fn repl(&mut self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        self.print(&mut output)?;
+        prompt(&mut output)?;
+
+        for line in input.lines() {
+            let line = line?;
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                [] => {}
+                ["quit" | "q"] => return Ok(()),
+                ["help" | "h"] => write!(output, "{HELP}")?,
+                ["step" | "s"] => self.step(1, &mut output)?,
+                ["step" | "s", n] => match n.parse() {
+                    Ok(n) => self.step(n, &mut output)?,
+                    Err(_) => writeln!(output, "{n:?} is not a number of cycles")?,
+                },
+                ["run" | "r"] => self.run(&mut output)?,
+                ["break" | "b", kind, n] => match (*kind, n.parse()) {
+                    ("cycle", Ok(n)) => self.add_breakpoint(Breakpoint::Cycle(n), &mut output)?,
+                    ("pc", Ok(n)) => self.add_breakpoint(Breakpoint::Pc(n), &mut output)?,
+                    _ => writeln!(output, "try break cycle N or break pc N")?,
+                },
+                ["watch" | "w", name] => match asm::register(name) {
+                    Ok(register) => {
+                        let value = self.cpu.register(register);
+                        self.watches.push((register, value));
+                        writeln!(output, "watching {register}, which is {value}")?;
+                    }
+                    Err(_) => writeln!(output, "there's no register called {name:?}")?,
+                },
+                ["print" | "p"] => self.print(&mut output)?,
+                ["crt"] => write!(output, "{}", self.crt.render())?,
+                _ => writeln!(output, "unknown command {line:?}; try help")?,
+            }
+            prompt(&mut output)?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs up to `n` cycles, mentioning watched registers that change on the way.
+    fn step(&mut self, n: usize, output: &mut impl Write) -> io::Result<()> {
+        for _ in 0..n {
+            if self.cpu.halted() {
+                break;
+            }
+            self.cpu.step(&mut [&mut self.crt]);
+            for change in self.watch_changes() {
+                writeln!(output, "{change}")?;
+            }
+        }
+        self.print(output)
+    }
+
+    fn run(&mut self, output: &mut impl Write) -> io::Result<()> {
+        while !self.cpu.halted() {
+            if self.cpu.out_of_cycles() {
+                writeln!(output, "gave up after {} cycles", self.cpu.cycles_taken)?;
+                break;
+            }
+
+            self.cpu.step(&mut [&mut self.crt]);
+
+            let changes = self.watch_changes();
+            for change in &changes {
+                writeln!(output, "{change}")?;
+            }
+            let hit = self.hit_breakpoint();
+            if let Some(breakpoint) = hit {
+                writeln!(output, "breakpoint at {breakpoint}")?;
+            }
+            if !changes.is_empty() || hit.is_some() {
+                break;
+            }
+        }
+        self.print(output)
+    }
+
+    fn add_breakpoint(
+        &mut self,
+        breakpoint: Breakpoint,
+        output: &mut impl Write,
+    ) -> io::Result<()> {
+        if let Breakpoint::Pc(pc) = breakpoint {
+            if let Some(op) = self.cpu.instructions.get(pc) {
+                writeln!(output, "breakpoint at pc {pc}: {op}")?;
+            } else {
+                writeln!(output, "there's no instruction at pc {pc}")?;
+                return Ok(());
+            }
+        } else {
+            writeln!(output, "breakpoint at {breakpoint}")?;
+        }
+        self.breakpoints.push(breakpoint);
+        Ok(())
+    }
+
+    /// Descriptions of the watched registers that have changed since last time.
+    fn watch_changes(&mut self) -> Vec<String> {
+        let mut changes = Vec::new();
+        for (register, last) in &mut self.watches {
+            let now = self.cpu.register(*register);
+            if now != *last {
+                changes.push(format!("{register}: {last} -> {now}"));
+                *last = now;
+            }
+        }
+        changes
+    }
+
+    fn hit_breakpoint(&self) -> Option<Breakpoint> {
+        let cpu = &self.cpu;
+        let just_started = cpu
+            .current_instruction()
+            .is_some_and(|op| cpu.cycles_left_for_current_instruction == op.cycles());
+        self.breakpoints
+            .iter()
+            .copied()
+            .find(|&breakpoint| match breakpoint {
+                Breakpoint::Cycle(cycle) => cpu.cycle() == cycle,
+                Breakpoint::Pc(pc) => cpu.program_counter == pc && just_started,
+            })
+    }
+
+    /// Where the CPU is up to:
+    ///
+    /// ```text
+    /// cycle 2, pc 1: addx 3 (2 of 2 cycles left)
+    /// x=1 y=0 z=0
+    /// sprite at 0..=2, drawing column 1
+    /// ```
+    fn print(&self, output: &mut impl Write) -> io::Result<()> {
+        let cpu = &self.cpu;
+        match cpu.current_instruction() {
+            Some(op) => writeln!(
+                output,
+                "cycle {}, pc {}: {op} ({} of {} cycles left)",
+                cpu.cycle(),
+                cpu.program_counter,
+                cpu.cycles_left_for_current_instruction,
+                op.cycles()
+            )?,
+            None => writeln!(output, "cycle {}, halted", cpu.cycle())?,
+        }
+        writeln!(
+            output,
+            "x={} y={} z={}",
+            cpu.x(),
+            cpu.register(Register::Y),
+            cpu.register(Register::Z)
+        )?;
+        writeln!(
+            output,
+            "sprite at {}..={}, drawing column {}",
+            cpu.x() - 1,
+            cpu.x() + 1,
+            (cpu.cycle() - 1) % CRT_WIDTH
+        )
+    }
+}
+
+fn prompt(output: &mut impl Write) -> io::Result<()> {
+    write!(output, "(debug) ")?;
+    output.flush()
+}
+
+impl Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Breakpoint::Cycle(cycle) => write!(f, "cycle {cycle}"),
+            Breakpoint::Pc(pc) => write!(f, "pc {pc}"),
+        }
+    }
+}
@@ -1,79 +1,279 @@
-use inpt::{self, Inpt};
+use std::env;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::process;
 
-#[derive(Inpt, Debug, Copy, Clone)]
-enum Instruction {
-    #[inpt(regex = "noop")]
-    Noop,
-    #[inpt(regex = r"addx\s+(-?\d+)")]
-    AddX(i64),
+mod asm;
+mod crt;
+mod debug;
+mod ocr;
+
+use crt::Crt;
+use debug::Debugger;
+
+/// Programs that haven't halted after this many cycles probably never will.
+const DEFAULT_CYCLE_LIMIT: usize = 1_000_000;
+
+/// How many cycles each instruction takes, by mnemonic.
+const CYCLES: [(&str, usize); 5] = [("noop", 1), ("add", 2), ("mul", 3), ("jmp", 1), ("jnz", 2)];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Register {
+    X,
+    Y,
+    Z,
 }
 
-const CRT_WIDTH: i64 = 40;
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Instruction {
+    Noop,
+    /// `addx N`, `addy N`, ...
+    Add(Register, i64),
+    Mul(Register, i64),
+    /// Jumps by an offset from this instruction.
+    Jmp(i64),
+    /// Jumps by an offset if the register isn't zero.
+    Jnz(Register, i64),
+}
 
+/// A CPU that runs one cycle per [`Cpu::tick`].
+///
+/// Between ticks, the registers hold what they are *during* the next cycle: an instruction's
+/// effect (including a jump) only shows up once its last cycle is over.
 #[derive(Debug)]
-struct CPU<'a> {
-    x: i64,
-    cycles_taken: i64,
+pub struct Cpu<'a> {
+    registers: [i64; 3],
+    cycles_taken: usize,
     program_counter: usize,
     cycles_left_for_current_instruction: usize,
+    cycle_limit: Option<usize>,
     instructions: &'a [Instruction],
 }
 
-#[inpt::main]
-fn main(instructions: Vec<Instruction>) {
-    let mut cpu = CPU::new(&instructions);
+/// Why [`Cpu::run`] stopped.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Stop {
+    Halted,
+    /// Gave up after this many cycles.
+    CycleLimit(usize),
+}
 
-    loop {
-        cpu.cycles_taken += 1;
-        cpu.cycles_left_for_current_instruction -= 1;
+/// Something that wants to see the CPU during every cycle.
+pub trait Observer {
+    fn during(&mut self, cpu: &Cpu);
+}
 
-        let current_pixel = (cpu.cycles_taken - 1) % CRT_WIDTH;
-        let sprite = cpu.x - 1..=cpu.x + 1;
+/// Adds up the signal strength (cycle number × X) during the chosen cycles.
+#[derive(Debug, Clone)]
+struct SignalProbe {
+    sampling: Sampling,
+    total: i64,
+}
 
-        if sprite.contains(&current_pixel) {
-            print!("#");
-        } else {
-            print!(".");
-        }
+#[derive(Debug, Clone)]
+enum Sampling {
+    Every { first: usize, interval: usize },
+    At(Vec<usize>),
+}
 
-        if current_pixel == CRT_WIDTH - 1 {
-            println!();
-        }
+#[derive(Debug)]
+struct Options {
+    probe: SignalProbe,
+    cycle_limit: usize,
+    /// Debug the program in this file, taking commands from stdin.
+    debug: Option<PathBuf>,
+}
 
-        let commit = cpu.cycles_left_for_current_instruction == 0;
-        if commit {
-            //println!("COMMIT");
-            cpu.commit_current_instruction();
-            cpu.program_counter += 1;
-            if let Some(op) = cpu.current_instruction() {
-                cpu.cycles_left_for_current_instruction = op.cycles();
-            }
+/// Usage: `day-10 [--probe FIRST/EVERY | --probe CYCLE,CYCLE,...] [--max-cycles N] [--debug FILE]`
+///
+/// Assembles the program on stdin (see [`asm::assemble`]) and runs it. Prints the sum of the
+/// signal strengths during the 20th cycle and every 40 cycles after, then the CRT, and then the
+/// letters on the CRT. `--probe` samples other cycles, either regularly (`--probe 20/40` is the
+/// default) or at a list of cycles (`--probe 20,60,100`). Programs are stopped after
+/// `--max-cycles` cycles (a million by default).
+///
+/// `--debug` steps through the program in a file instead, reading debugger commands from stdin
+/// (`help` lists them).
+fn main() {
+    let mut options = parse_args(env::args().skip(1));
+
+    let source = match &options.debug {
+        Some(path) => fs::read_to_string(path).unwrap_or_else(|error| {
+            eprintln!("{}: {error}", path.display());
+            process::exit(1);
+        }),
+        None => {
+            let mut source = String::new();
+            io::stdin()
+                .read_to_string(&mut source)
+                .expect("read program from stdin");
+            source
         }
+    };
+    let instructions = asm::assemble(&source).unwrap_or_else(|error| {
+        eprintln!("{error}");
+        process::exit(1);
+    });
+
+    if options.debug.is_some() {
+        let cpu = Cpu::new(&instructions).with_cycle_limit(options.cycle_limit);
+        Debugger::new(cpu)
+            .repl(io::stdin().lock(), io::stdout().lock())
+            .expect("talk to the terminal");
+        return;
+    }
 
-        if cpu.current_instruction().is_none() {
-            break;
+    let mut crt = Crt::new();
+    let mut cpu = Cpu::new(&instructions).with_cycle_limit(options.cycle_limit);
+    if let Stop::CycleLimit(cycles) = cpu.run(&mut [&mut crt, &mut options.probe]) {
+        eprintln!("gave up after {cycles} cycles");
+    }
+
+    println!("{}", options.probe.total);
+    print!("{}", crt.render());
+    match ocr::read(crt.pixels()) {
+        Ok(text) => println!("{text}"),
+        Err(error) => eprintln!("{error}"),
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Options {
+    let mut options = Options {
+        probe: SignalProbe::periodic(20, 40),
+        cycle_limit: DEFAULT_CYCLE_LIMIT,
+        debug: None,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--probe" => {
+                let cycles = args.next().expect("--probe needs cycles");
+                options.probe = SignalProbe::parse(&cycles);
+            }
+            "--max-cycles" => {
+                options.cycle_limit = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .expect("--max-cycles needs a number of cycles");
+            }
+            "--debug" => {
+                let path = args.next().expect("--debug needs a program");
+                options.debug = Some(path.into());
+            }
+            _ => panic!("unknown argument {arg}"),
         }
     }
+
+    options
 }
 
-impl<'a> CPU<'a> {
+impl<'a> Cpu<'a> {
     fn new(instructions: &'a [Instruction]) -> Self {
-        let first = instructions[0];
-
-        CPU {
-            x: 1,
+        Cpu {
+            registers: [1, 0, 0],
             cycles_taken: 0,
             program_counter: 0,
             instructions,
-            cycles_left_for_current_instruction: first.cycles(),
+            cycle_limit: None,
+            cycles_left_for_current_instruction: instructions.first().map_or(0, |op| op.cycles()),
         }
     }
 
-    fn commit_current_instruction(&mut self) {
-        use Instruction::*;
-        if let Some(AddX(op)) = self.current_instruction() {
-            self.x += op;
+    /// Makes [`Cpu::run`] give up once this many cycles have run.
+    fn with_cycle_limit(mut self, cycles: usize) -> Self {
+        self.cycle_limit = Some(cycles);
+        self
+    }
+
+    /// Runs the program to the end (or the cycle limit), showing every cycle to every observer.
+    fn run(&mut self, observers: &mut [&mut dyn Observer]) -> Stop {
+        while !self.halted() {
+            if self.out_of_cycles() {
+                return Stop::CycleLimit(self.cycles_taken);
+            }
+            self.step(observers);
+        }
+        Stop::Halted
+    }
+
+    /// Shows the current cycle to every observer, then finishes it.
+    fn step(&mut self, observers: &mut [&mut dyn Observer]) {
+        if self.halted() {
+            return;
         }
+        for observer in observers.iter_mut() {
+            observer.during(self);
+        }
+        self.tick();
+    }
+
+    /// Finishes the current cycle. Does nothing once the program is over.
+    fn tick(&mut self) {
+        if self.halted() {
+            return;
+        }
+
+        self.cycles_taken += 1;
+        self.cycles_left_for_current_instruction -= 1;
+
+        if self.cycles_left_for_current_instruction == 0 {
+            self.program_counter = self.commit_current_instruction();
+            if let Some(op) = self.current_instruction() {
+                self.cycles_left_for_current_instruction = op.cycles();
+            }
+        }
+    }
+
+    fn halted(&self) -> bool {
+        self.current_instruction().is_none()
+    }
+
+    fn out_of_cycles(&self) -> bool {
+        self.cycle_limit
+            .is_some_and(|limit| self.cycles_taken >= limit)
+    }
+
+    /// The cycle that's about to run, counting from 1.
+    fn cycle(&self) -> usize {
+        self.cycles_taken + 1
+    }
+
+    fn x(&self) -> i64 {
+        self.register(Register::X)
+    }
+
+    fn register(&self, register: Register) -> i64 {
+        self.registers[register as usize]
+    }
+
+    /// Does what the current instruction says, and returns where to go next. Jumps anywhere
+    /// outside the program halt it.
+    fn commit_current_instruction(&mut self) -> usize {
+        use Instruction::*;
+        let next = self.program_counter + 1;
+        let Some(op) = self.current_instruction() else {
+            return next;
+        };
+
+        let offset = match op {
+            Noop => return next,
+            Add(register, amount) => {
+                self.registers[register as usize] += amount;
+                return next;
+            }
+            Mul(register, factor) => {
+                self.registers[register as usize] *= factor;
+                return next;
+            }
+            Jmp(offset) => offset,
+            Jnz(register, offset) if self.register(register) != 0 => offset,
+            Jnz(..) => return next,
+        };
+        self.program_counter
+            .checked_add_signed(offset as isize)
+            .unwrap_or(self.instructions.len())
     }
 
     fn current_instruction(&self) -> Option<Instruction> {
@@ -82,11 +282,448 @@ impl<'a> CPU<'a> {
 }
 
 impl Instruction {
+    fn mnemonic(self) -> &'static str {
+        use Instruction::*;
+        match self {
+            Noop => "noop",
+            Add(..) => "add",
+            Mul(..) => "mul",
+            Jmp(_) => "jmp",
+            Jnz(..) => "jnz",
+        }
+    }
+
     fn cycles(self) -> usize {
+        CYCLES
+            .iter()
+            .find(|&&(mnemonic, _)| mnemonic == self.mnemonic())
+            .map(|&(_, cycles)| cycles)
+            .expect("every instruction is in the cycle table")
+    }
+}
+
+impl Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Register::X => "x",
+            Register::Y => "y",
+            Register::Z => "z",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// As it would be written in assembly, with jumps as offsets.
+impl Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use Instruction::*;
         match self {
-            AddX(_) => 2,
-            Noop => 1,
+            Noop => write!(f, "noop"),
+            Add(register, amount) => write!(f, "add{register} {amount}"),
+            Mul(register, factor) => write!(f, "mul {register} {factor}"),
+            Jmp(offset) => write!(f, "jmp {offset}"),
+            Jnz(register, offset) => write!(f, "jnz {register} {offset}"),
         }
     }
 }
+
+impl SignalProbe {
+    /// During cycle `first`, and every `interval` cycles after that.
+    fn periodic(first: usize, interval: usize) -> Self {
+        assert!(interval > 0, "can't sample every 0 cycles");
+        SignalProbe {
+            sampling: Sampling::Every { first, interval },
+            total: 0,
+        }
+    }
+
+    /// During each of `cycles`.
+    fn at(cycles: &[usize]) -> Self {
+        SignalProbe {
+            sampling: Sampling::At(cycles.to_vec()),
+            total: 0,
+        }
+    }
+
+    /// `FIRST/EVERY`, or a comma-separated list of cycles.
+    fn parse(spec: &str) -> Self {
+        let number = |s: &str| -> usize {
+            s.trim()
+                .parse()
+                .unwrap_or_else(|_| panic!("{s:?} is not a cycle number"))
+        };
+        match spec.split_once('/') {
+            Some((first, every)) => SignalProbe::periodic(number(first), number(every)),
+            None => SignalProbe::at(&spec.split(',').map(number).collect::<Vec<_>>()),
+        }
+    }
+
+    fn samples(&self, cycle: usize) -> bool {
+        match &self.sampling {
+            &Sampling::Every { first, interval } => {
+                cycle >= first && (cycle - first).is_multiple_of(interval)
+            }
+            Sampling::At(cycles) => cycles.contains(&cycle),
+        }
+    }
+}
+
+impl Observer for SignalProbe {
+    fn during(&mut self, cpu: &Cpu) {
+        if self.samples(cpu.cycle()) {
+            self.total += cpu.cycle() as i64 * cpu.x();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn program(text: &str) -> Vec<Instruction> {
+        asm::assemble(text).unwrap()
+    }
+
+    /// X during every cycle.
+    fn trace(instructions: &[Instruction]) -> Vec<i64> {
+        let mut xs = Vec::new();
+        let mut record = |cpu: &Cpu| xs.push(cpu.x());
+        Cpu::new(instructions).run(&mut [&mut record]);
+        xs
+    }
+
+    impl<F: FnMut(&Cpu)> Observer for F {
+        fn during(&mut self, cpu: &Cpu) {
+            self(cpu)
+        }
+    }
+
+    #[test]
+    fn test_small_program() {
+        let instructions = program(include_str!("../test-0.txt"));
+        assert_eq!(vec![1, 1, 1, 4, 4], trace(&instructions));
+
+        let mut cpu = Cpu::new(&instructions);
+        for _ in 0..5 {
+            cpu.tick();
+        }
+        assert!(cpu.halted());
+        assert_eq!(-1, cpu.x());
+        assert_eq!(6, cpu.cycle());
+
+        // Ticking a halted CPU does nothing:
+        cpu.tick();
+        assert_eq!(6, cpu.cycle());
+        assert!(trace(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_both_parts_in_one_run() {
+        let instructions = program(include_str!("../test-1.txt"));
+        let mut probe = SignalProbe::periodic(20, 40);
+        let mut crt = Crt::new();
+        Cpu::new(&instructions).run(&mut [&mut probe, &mut crt]);
+
+        assert_eq!(13140, probe.total);
+        assert_eq!(
+            "\
+##..##..##..##..##..##..##..##..##..##..
+###...###...###...###...###...###...###.
+####....####....####....####....####....
+#####.....#####.....#####.....#####.....
+######......######......######......####
+#######.......#######.......#######.....
+",
+            crt.render()
+        );
+    }
+
+    #[test]
+    fn test_probe_cycles() {
+        let instructions = program(include_str!("../test-1.txt"));
+        let run = |mut probe: SignalProbe| {
+            Cpu::new(&instructions).run(&mut [&mut probe]);
+            probe.total
+        };
+
+        // From the puzzle description:
+        assert_eq!(420, run(SignalProbe::at(&[20])));
+        assert_eq!(1140, run(SignalProbe::at(&[60])));
+        assert_eq!(420 + 2940, run(SignalProbe::parse("20,140")));
+        assert_eq!(13140, run(SignalProbe::parse("20/40")));
+        assert_eq!(
+            run(SignalProbe::at(&[20, 60, 100, 140, 180, 220])),
+            run(SignalProbe::parse("20/40"))
+        );
+    }
+
+    #[test]
+    fn test_assembler() {
+        use Instruction::*;
+        use Register::*;
+
+        let source = "\
+; count down from 3
+        addy 3
+loop:   addx 2      # comments go anywhere
+        mul z, -4
+        addy -1
+        jnz y loop
+end:
+";
+        let instructions = program(source);
+        assert_eq!(
+            vec![Add(Y, 3), Add(X, 2), Mul(Z, -4), Add(Y, -1), Jnz(Y, -3)],
+            instructions
+        );
+
+        // Printing the instructions gives source that assembles to the same thing:
+        let printed: Vec<String> = instructions.iter().map(|op| op.to_string()).collect();
+        assert_eq!("jnz y -3", printed[4]);
+        assert_eq!(instructions, program(&printed.join("\n")));
+
+        // Jumping straight to the end halts:
+        assert_eq!(vec![Jmp(2), Noop], program("jmp end\nnoop\nend:"));
+        assert_eq!(vec![Jmp(0)], program("a: b: jmp b"));
+    }
+
+    #[test]
+    fn test_assembler_errors() {
+        use asm::AsmErrorKind::*;
+        let error = |source: &str| asm::assemble(source).unwrap_err();
+
+        assert_eq!(
+            asm::AsmError {
+                line: 2,
+                kind: UnknownInstruction("sub".to_owned())
+            },
+            error("noop\nsub 3")
+        );
+        assert_eq!(UnknownInstruction("addw".to_owned()), error("addw 1").kind);
+        assert_eq!(UnknownRegister("w".to_owned()), error("mul w 2").kind);
+        assert_eq!(NotANumber("two".to_owned()), error("mul x two").kind);
+        assert_eq!(WrongOperands("noop", "nothing"), error("noop 1").kind);
+        assert_eq!(WrongOperands("add", "a number"), error("addx").kind);
+        assert_eq!(
+            UnknownLabel("nowhere".to_owned()),
+            error("jmp nowhere").kind
+        );
+        assert_eq!(
+            DuplicateLabel("a".to_owned()),
+            error("a: noop\na: noop").kind
+        );
+        assert_eq!(JumpOutOfRange(-1), error("jmp -1").kind);
+        assert_eq!(JumpOutOfRange(3), error("noop\njmp 3").kind);
+        assert_eq!(
+            "line 1: there's no label called \"nowhere\"",
+            error("jmp nowhere").to_string()
+        );
+    }
+
+    #[test]
+    fn test_jumps() {
+        let instructions = program(
+            "\
+        addy 3
+loop:   addx 2
+        addy -1
+        jnz y loop
+        mul x 10
+",
+        );
+        // Setting up, then three times round the loop at 2 + 2 + 2 cycles a go, then the mul:
+        assert_eq!(
+            vec![
+                1, 1, //
+                1, 1, 3, 3, 3, 3, //
+                3, 3, 5, 5, 5, 5, //
+                5, 5, 7, 7, 7, 7, //
+                7, 7, 7,
+            ],
+            trace(&instructions)
+        );
+
+        let mut cpu = Cpu::new(&instructions);
+        cpu.run(&mut []);
+        assert_eq!(70, cpu.x());
+        assert_eq!(0, cpu.register(Register::Y));
+
+        // A jump takes effect after its last cycle, like any other instruction:
+        let instructions = program("jmp skip\naddx 100\nskip: addx 1");
+        assert_eq!(vec![1, 1, 1], trace(&instructions));
+    }
+
+    #[test]
+    fn test_cycle_limit() {
+        let instructions = program("forever: addx 1\njmp forever");
+        let mut cpu = Cpu::new(&instructions).with_cycle_limit(30);
+        let mut cycles = 0;
+        let mut count = |_: &Cpu| cycles += 1;
+        assert_eq!(Stop::CycleLimit(30), cpu.run(&mut [&mut count]));
+        assert_eq!(30, cycles);
+        assert_eq!(11, cpu.x());
+
+        // Programs that halt in time aren't affected:
+        let instructions = program(include_str!("../test-0.txt"));
+        let mut cpu = Cpu::new(&instructions).with_cycle_limit(5);
+        assert_eq!(Stop::Halted, cpu.run(&mut []));
+    }
+
+    /// What the debugger says in reply to `commands`, with the prompts taken out.
+    fn debug(instructions: &[Instruction], commands: &str) -> String {
+        let mut output = Vec::new();
+        Debugger::new(Cpu::new(instructions).with_cycle_limit(100))
+            .repl(commands.as_bytes(), &mut output)
+            .unwrap();
+        String::from_utf8(output).unwrap().replace("(debug) ", "")
+    }
+
+    #[test]
+    fn test_debugger() {
+        let instructions = program(include_str!("../test-0.txt"));
+        assert_eq!(
+            "\
+cycle 1, pc 0: noop (1 of 1 cycles left)
+x=1 y=0 z=0
+sprite at 0..=2, drawing column 0
+cycle 3, pc 1: addx 3 (1 of 2 cycles left)
+x=1 y=0 z=0
+sprite at 0..=2, drawing column 2
+watching x, which is 1
+x: 1 -> 4
+cycle 4, pc 2: addx -5 (2 of 2 cycles left)
+x=4 y=0 z=0
+sprite at 3..=5, drawing column 3
+###
+",
+            debug(&instructions, "step 2\nwatch x\nstep\ncrt\nquit\nrun\n")
+        );
+
+        // Breakpoints on an instruction stop every time round a loop:
+        let instructions = program(
+            "\
+        addy 2
+loop:   addy -1
+        jnz y loop
+        addx 10
+",
+        );
+        let output = debug(&instructions, "break pc 1\nrun\nrun\nrun\n");
+        let stops: Vec<&str> = output
+            .lines()
+            .filter(|line| line.starts_with("cycle"))
+            .collect();
+        assert_eq!(
+            vec![
+                "cycle 1, pc 0: addy 2 (2 of 2 cycles left)",
+                "cycle 3, pc 1: addy -1 (2 of 2 cycles left)",
+                "cycle 7, pc 1: addy -1 (2 of 2 cycles left)",
+                "cycle 13, halted",
+            ],
+            stops
+        );
+        assert!(output.starts_with("cycle 1, pc 0: addy 2 (2 of 2 cycles left)\nx=1 y=0 z=0\n"));
+        assert!(output.contains("breakpoint at pc 1: addy -1\n"));
+
+        // Stopping at a cycle, and programs that go forever:
+        let instructions = program("forever: jmp forever");
+        let output = debug(&instructions, "b cycle 40\nr\nr\nbreak pc 3\nnonsense\n");
+        assert!(output.contains("breakpoint at cycle 40\ncycle 40, pc 0: jmp 0"));
+        assert!(output.contains("gave up after 100 cycles\ncycle 101, pc 0: jmp 0"));
+        assert!(output.contains("there's no instruction at pc 3\n"));
+        assert!(output.ends_with("unknown command \"nonsense\"; try help\n"));
+    }
+
+    /// A screen from `#` and `.`, ignoring line breaks.
+    fn pixels(text: &str) -> Vec<bool> {
+        text.chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| c == '#')
+            .collect()
+    }
+
+    /// `letters` as they would be drawn on the CRT, padded with blanks to fill the screen.
+    fn screen(letters: &str) -> Vec<bool> {
+        let glyph = |letter: char| {
+            alphabet()
+                .into_iter()
+                .find(|&(c, _)| c == letter)
+                .map_or(["...."; 6], |(_, glyph)| glyph)
+        };
+        let mut text = String::new();
+        for row in 0..6 {
+            for letter in format!("{letters:8}").chars() {
+                text.push_str(glyph(letter)[row]);
+                text.push('.');
+            }
+            text.push('\n');
+        }
+        pixels(&text)
+    }
+
+    /// Every letter, drawn out separately from the font in `ocr`.
+    fn alphabet() -> Vec<(char, [&'static str; 6])> {
+        let letters = "ABCEFGHIJKLOPRSUZ";
+        let text = "\
+.##..###...##..####.####..##..#..#..###...##.#..#.#.....##..###..###...###.#..#.####.
+#..#.#..#.#..#.#....#....#..#.#..#...#.....#.#.#..#....#..#.#..#.#..#.#....#..#....#.
+#..#.###..#....###..###..#....####...#.....#.##...#....#..#.#..#.#..#.#....#..#...#..
+####.#..#.#....#....#....#.##.#..#...#.....#.#.#..#....#..#.###..###...##..#..#..#...
+#..#.#..#.#..#.#....#....#..#.#..#...#..#..#.#.#..#....#..#.#....#.#.....#.#..#.#....
+#..#.###...##..####.#.....###.#..#..###..##..#..#.####..##..#....#..#.###...##..####.
+";
+        let rows: Vec<&'static str> = text.lines().collect();
+        letters
+            .chars()
+            .enumerate()
+            .map(|(i, letter)| {
+                let mut glyph = [""; 6];
+                for (row, line) in rows.iter().enumerate() {
+                    glyph[row] = &line[i * 5..i * 5 + 4];
+                }
+                (letter, glyph)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_ocr_every_letter() {
+        // Eight letters at a time, which is all that fits on the screen:
+        assert_eq!(Ok("ABCEFGHI".to_owned()), ocr::read(&screen("ABCEFGHI")));
+        assert_eq!(Ok("JKLOPRSU".to_owned()), ocr::read(&screen("JKLOPRSU")));
+        assert_eq!(Ok("Z       ".to_owned()), ocr::read(&screen("Z")));
+        assert_eq!(Ok("ZEBRA IS".to_owned()), ocr::read(&screen("ZEBRA IS")));
+    }
+
+    #[test]
+    fn test_ocr_unknown_glyphs() {
+        let instructions = program(include_str!("../test-1.txt"));
+        let mut crt = Crt::new();
+        Cpu::new(&instructions).run(&mut [&mut crt]);
+
+        // The stripes in the example aren't letters at all:
+        let Err(ocr::OcrError::UnknownGlyphs(unknown)) = ocr::read(crt.pixels()) else {
+            panic!("the stripes should not be read as letters");
+        };
+        assert_eq!(8, unknown.len());
+        assert_eq!(0, unknown[0].position);
+        assert_eq!(
+            "##..#\n###..\n####.\n#####\n#####\n#####",
+            unknown[0].bitmap
+        );
+
+        // A letter with a pixel out of place:
+        let mut smudged = screen("HI");
+        smudged[1] = true;
+        let error = ocr::read(&smudged).unwrap_err();
+        assert_eq!(
+            "unknown glyph at position 0:\n##.#.\n#..#.\n####.\n#..#.\n#..#.\n#..#.",
+            error.to_string()
+        );
+
+        assert_eq!(
+            Err(ocr::OcrError::WrongSize { pixels: 5 }),
+            ocr::read(&[false; 5])
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use std::fmt::{self, Display};
+
+use crate::crt::{CRT_HEIGHT, CRT_WIDTH};
+
+/// Each letter is four pixels wide, with a blank column after it.
+const GLYPH_WIDTH: usize = 4;
+const CELL_WIDTH: usize = GLYPH_WIDTH + 1;
+
+/// Every letter of the Advent font that's known to turn up on the CRT.
+const FONT: [(char, [&str; CRT_HEIGHT]); 17] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OcrError {
+    /// The screen isn't `CRT_WIDTH` × `CRT_HEIGHT`.
+    WrongSize { pixels: usize },
+    /// Glyphs that don't look like any letter in the font.
+    UnknownGlyphs(Vec<UnknownGlyph>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownGlyph {
+    /// Counting from 0 at the left of the screen.
+    pub position: usize,
+    /// The whole cell, spacer column included, as `#` and `.`, a row per line.
+    pub bitmap: String,
+}
+
+/// Reads the letters on a full screen of pixels, row after row. Blank cells read as spaces.
+pub fn read(pixels: &[bool]) -> Result<String, OcrError> {
+    if pixels.len() != CRT_WIDTH * CRT_HEIGHT {
+        return Err(OcrError::WrongSize {
+            pixels: pixels.len(),
+        });
+    }
+
+    let mut text = String::new();
+    let mut unknown = Vec::new();
+    for position in 0..CRT_WIDTH / CELL_WIDTH {
+        let rows: Vec<String> = pixels
+            .chunks(CRT_WIDTH)
+            .map(|row| {
+                row[position * CELL_WIDTH..(position + 1) * CELL_WIDTH]
+                    .iter()
+                    .map(|&lit| if lit { '#' } else { '.' })
+                    .collect()
+            })
+            .collect();
+
+        match recognise(&rows) {
+            Some(letter) => text.push(letter),
+            None => unknown.push(UnknownGlyph {
+                position,
+                bitmap: rows.join("\n"),
+            }),
+        }
+    }
+
+    if unknown.is_empty() {
+        Ok(text)
+    } else {
+        Err(OcrError::UnknownGlyphs(unknown))
+    }
+}
+
+/// `rows` are a whole cell wide; anything in the spacer column means it's not a letter.
+fn recognise(rows: &[String]) -> Option<char> {
+    if rows.iter().any(|row| row.ends_with('#')) {
+        return None;
+    }
+    if rows.iter().all(|row| !row.contains('#')) {
+        return Some(' ');
+    }
+
+    FONT.iter()
+        .find(|(_, glyph)| {
+            glyph
+                .iter()
+                .zip(rows)
+                .all(|(glyph_row, row)| row.starts_with(glyph_row))
+        })
+        .map(|&(letter, _)| letter)
+}
+
+impl Display for OcrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OcrError::WrongSize { pixels } => write!(
+                f,
+                "expected a {CRT_WIDTH}×{CRT_HEIGHT} screen, but there are {pixels} pixels"
+            ),
+            OcrError::UnknownGlyphs(glyphs) => {
+                for (i, glyph) in glyphs.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    writeln!(f, "unknown glyph at position {}:", glyph.position)?;
+                    write!(f, "{}", glyph.bitmap)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
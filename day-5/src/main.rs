@@ -1,6 +1,10 @@
 use inpt::{inpt, Inpt};
 use std::io::{self, BufRead};
 
+mod stacks;
+
+use stacks::ChunkedStack;
+
 #[derive(Inpt, Debug)]
 #[inpt(regex = r"move (\d+) from (\d+) to (\d+)")]
 struct Move {
@@ -13,16 +17,31 @@ struct Move {
 #[inpt(regex = r"\[(\w)\]")]
 struct Crate(char);
 
-trait VecExt<T> {
-    fn last(&self) -> T;
-}
-
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let stdin = io::stdin();
+    let mut lines = stdin.lock().lines().map(|line| line.unwrap());
+
+    let stacks = read_stacks(&mut lines);
+
+    // Parse moves.
+    let moves: Vec<Move> = lines.map(|line| inpt(&line).unwrap()).collect();
+
+    let stacks = rearrange(stacks, &moves);
+    let message: String = stacks
+        .iter()
+        .skip(1)
+        .map(|stack| stack.top().expect("stack should not be empty"))
+        .collect();
+
+    println!("{message}");
+
+    Ok(())
+}
 
+/// Reads the drawing of the initial stacks, up to (and including) the blank line.
+fn read_stacks(lines: impl Iterator<Item = String>) -> Vec<Vec<char>> {
     let mut crate_lines = Vec::new();
-    for line in stdin.lock().lines() {
-        let line = line.unwrap();
+    for line in lines {
         if line.trim() == "" {
             break;
         }
@@ -60,41 +79,123 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         stacks.push(stack);
     }
 
-    // Parse moves.
-    let moves: Vec<Move> = stdin
-        .lock()
-        .lines()
-        .map(|line| {
-            let line = line.unwrap();
-            inpt(&line).unwrap()
-        })
-        .collect();
+    stacks
+}
 
-    for m in moves.into_iter() {
-        let end = stacks[m.source].len();
-        let start = ((end as isize) - (m.quantity as isize)) as usize;
+fn rearrange(stacks: Vec<Vec<char>>, moves: &[Move]) -> Vec<ChunkedStack> {
+    let mut stacks: Vec<_> = stacks.into_iter().map(ChunkedStack::from_vec).collect();
 
-        let tail = stacks[m.source][start..end].to_vec();
-        stacks[m.destination].extend_from_slice(&tail);
-        stacks[m.source].resize_with(start, || panic!());
+    for m in moves {
+        let block = stacks[m.source].split_off_top(m.quantity as usize);
+        stacks[m.destination].push_block(block);
     }
 
-    let message: String = stacks
-        .into_iter()
-        .skip(1)
-        .map(|stack| stack.last())
-        .collect();
+    stacks
+}
 
-    println!("{message}");
+#[cfg(test)]
+mod test {
+    use super::*;
+    use proptest::prelude::*;
+    use proptest::strategy::ValueTree;
+    use proptest::test_runner::TestRunner;
+    use std::time::Instant;
+
+    /// The original algorithm: copy the tail of the source stack onto the destination.
+    fn rearrange_by_copying(mut stacks: Vec<Vec<char>>, moves: &[Move]) -> Vec<Vec<char>> {
+        for m in moves {
+            let end = stacks[m.source].len();
+            let start = ((end as isize) - (m.quantity as isize)) as usize;
+
+            let tail = stacks[m.source][start..end].to_vec();
+            stacks[m.destination].extend_from_slice(&tail);
+            stacks[m.source].resize_with(start, || panic!());
+        }
 
-    Ok(())
-}
+        stacks
+    }
+
+    /// `n_stacks` stacks of `height` crates each, and up to `n_moves` valid moves between them.
+    fn procedure(
+        n_stacks: usize,
+        height: usize,
+        n_moves: usize,
+    ) -> impl Strategy<Value = (Vec<Vec<char>>, Vec<Move>)> {
+        let crate_ = (b'A'..=b'Z').prop_map(char::from);
+        let stack = proptest::collection::vec(crate_, height);
+        let stacks = proptest::collection::vec(stack, n_stacks);
+        let picks =
+            proptest::collection::vec((1..=n_stacks, 1..=n_stacks, any::<usize>()), n_moves);
+
+        (stacks, picks).prop_map(|(stacks, picks)| {
+            let stacks: Vec<Vec<char>> = std::iter::once(vec![]).chain(stacks).collect();
+            let mut heights: Vec<_> = stacks.iter().map(Vec::len).collect();
+            let mut moves = Vec::with_capacity(picks.len());
+            for (source, destination, quantity) in picks {
+                if source == destination || heights[source] == 0 {
+                    continue;
+                }
+                let quantity = 1 + quantity % heights[source];
+                heights[source] -= quantity;
+                heights[destination] += quantity;
+                moves.push(Move {
+                    quantity: quantity as i32,
+                    source,
+                    destination,
+                });
+            }
+            (stacks, moves)
+        })
+    }
+
+    fn assert_same_result(stacks: Vec<Vec<char>>, moves: &[Move]) {
+        let expected = rearrange_by_copying(stacks.clone(), moves);
+        let stacks = rearrange(stacks, moves);
+        assert!(stacks.iter().all(ChunkedStack::is_heap_ordered));
+        let actual: Vec<_> = stacks.iter().map(ChunkedStack::to_vec).collect();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_given_input() {
+        let input = include_str!("../test-0.txt");
+        let mut lines = input.lines().map(str::to_owned);
+        let stacks = read_stacks(&mut lines);
+        let moves: Vec<Move> = lines.map(|line| inpt(&line).unwrap()).collect();
+
+        let message: String = rearrange(stacks, &moves)
+            .iter()
+            .skip(1)
+            .map(|stack| stack.top().unwrap())
+            .collect();
+        assert_eq!("MCD", message);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_rearrange_matches_copying((stacks, moves) in procedure(9, 20, 500)) {
+            assert_same_result(stacks, &moves);
+        }
+    }
 
-impl<T> VecExt<T> for Vec<T>
-where
-    T: Copy,
-{
-    fn last(&self) -> T {
-        self[self.len() - 1]
+    /// Run with `cargo test --release -p day-5 -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_huge_procedure() {
+        let (stacks, moves) = procedure(9, 100_000, 200_000)
+            .new_tree(&mut TestRunner::deterministic())
+            .unwrap()
+            .current();
+
+        let start = Instant::now();
+        let expected = rearrange_by_copying(stacks.clone(), &moves);
+        println!("copying: {:?}", start.elapsed());
+
+        let start = Instant::now();
+        let actual = rearrange(stacks, &moves);
+        println!("rope:    {:?}", start.elapsed());
+
+        let actual: Vec<_> = actual.iter().map(ChunkedStack::to_vec).collect();
+        assert_eq!(expected, actual);
     }
 }
@@ -0,0 +1,208 @@
+use std::cell::Cell;
+use std::ops::Range;
+use std::rc::Rc;
+
+/// A stack of crates stored as a rope: a balanced tree (an implicit treap) of shared, immutable
+/// chunks.
+///
+/// Moving a block of crates from one stack to another never copies the crates themselves. The
+/// block is split off the source in O(log n) expected time (at most one chunk is cut in two, and
+/// both halves keep pointing at the same buffer), then concatenated onto the destination, also in
+/// O(log n) expected time.
+#[derive(Debug, Default)]
+pub struct ChunkedStack {
+    root: Link,
+}
+
+type Link = Option<Box<Node>>;
+
+#[derive(Debug)]
+struct Node {
+    chunk: Chunk,
+    priority: u64,
+    /// Number of crates in this whole subtree.
+    size: usize,
+    left: Link,
+    right: Link,
+}
+
+#[derive(Debug, Clone)]
+struct Chunk {
+    buffer: Rc<[char]>,
+    range: Range<usize>,
+}
+
+impl ChunkedStack {
+    pub fn from_vec(crates: Vec<char>) -> Self {
+        if crates.is_empty() {
+            return ChunkedStack::default();
+        }
+
+        let len = crates.len();
+        let chunk = Chunk {
+            buffer: crates.into(),
+            range: 0..len,
+        };
+        ChunkedStack {
+            root: Some(Node::new(chunk)),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        size(&self.root)
+    }
+
+    pub fn top(&self) -> Option<char> {
+        let mut node = self.root.as_ref()?;
+        while let Some(right) = node.right.as_ref() {
+            node = right;
+        }
+        Some(node.chunk.buffer[node.chunk.range.end - 1])
+    }
+
+    /// Removes the top `quantity` crates and returns them as their own stack.
+    pub fn split_off_top(&mut self, quantity: usize) -> ChunkedStack {
+        let len = self.len();
+        assert!(quantity <= len, "Not enough crates on the stack");
+
+        let (bottom, top) = split(self.root.take(), len - quantity);
+        self.root = bottom;
+        ChunkedStack { root: top }
+    }
+
+    /// Puts a whole block on top of this stack, keeping its order.
+    pub fn push_block(&mut self, block: ChunkedStack) {
+        self.root = merge(self.root.take(), block.root);
+    }
+
+    #[cfg(test)]
+    pub fn to_vec(&self) -> Vec<char> {
+        let mut crates = Vec::with_capacity(self.len());
+        let mut pending = Vec::new();
+        let mut current = self.root.as_deref();
+
+        // In-order traversal, bottom of the stack first.
+        loop {
+            while let Some(node) = current {
+                pending.push(node);
+                current = node.left.as_deref();
+            }
+            let Some(node) = pending.pop() else {
+                break;
+            };
+            crates.extend_from_slice(&node.chunk.buffer[node.chunk.range.clone()]);
+            current = node.right.as_deref();
+        }
+
+        crates
+    }
+
+    /// Whether no node outranks its parent, which is what keeps the tree balanced.
+    #[cfg(test)]
+    pub fn is_heap_ordered(&self) -> bool {
+        let mut pending: Vec<&Node> = self.root.as_deref().into_iter().collect();
+        while let Some(node) = pending.pop() {
+            for child in [&node.left, &node.right].into_iter().flatten() {
+                if child.priority > node.priority {
+                    return false;
+                }
+                pending.push(child);
+            }
+        }
+        true
+    }
+}
+
+impl Node {
+    fn new(chunk: Chunk) -> Box<Node> {
+        Node::with_priority(chunk, next_priority())
+    }
+
+    fn with_priority(chunk: Chunk, priority: u64) -> Box<Node> {
+        Box::new(Node {
+            size: chunk.range.len(),
+            chunk,
+            priority,
+            left: None,
+            right: None,
+        })
+    }
+
+    fn update_size(&mut self) {
+        self.size = size(&self.left) + self.chunk.range.len() + size(&self.right);
+    }
+}
+
+fn size(link: &Link) -> usize {
+    link.as_ref().map_or(0, |node| node.size)
+}
+
+/// Concatenates two ropes: every crate in `bottom` ends up below every crate in `top`.
+fn merge(bottom: Link, top: Link) -> Link {
+    match (bottom, top) {
+        (None, top) => top,
+        (bottom, None) => bottom,
+        (Some(mut bottom), Some(mut top)) => {
+            if bottom.priority > top.priority {
+                bottom.right = merge(bottom.right.take(), Some(top));
+                bottom.update_size();
+                Some(bottom)
+            } else {
+                top.left = merge(Some(bottom), top.left.take());
+                top.update_size();
+                Some(top)
+            }
+        }
+    }
+}
+
+/// Splits a rope so that the first half contains exactly the bottom `n` crates.
+fn split(link: Link, n: usize) -> (Link, Link) {
+    let Some(mut node) = link else {
+        return (None, None);
+    };
+
+    let left_size = size(&node.left);
+    let chunk_len = node.chunk.range.len();
+
+    if n <= left_size {
+        let (left, right) = split(node.left.take(), n);
+        node.left = right;
+        node.update_size();
+        (left, Some(node))
+    } else if n >= left_size + chunk_len {
+        let (left, right) = split(node.right.take(), n - left_size - chunk_len);
+        node.right = left;
+        node.update_size();
+        (Some(node), right)
+    } else {
+        // The split point is inside this node's chunk: cut it in two.
+        let split_at = node.chunk.range.start + (n - left_size);
+        let top = Chunk {
+            buffer: Rc::clone(&node.chunk.buffer),
+            range: split_at..node.chunk.range.end,
+        };
+        node.chunk.range.end = split_at;
+        let right = node.right.take();
+        node.update_size();
+        // The top half takes this node's place, so it keeps this node's priority: a fresh one
+        // could outrank whatever the right half ends up under.
+        let top = Node::with_priority(top, node.priority);
+        (Some(node), merge(Some(top), right))
+    }
+}
+
+/// Pseudo-random treap priorities (splitmix64 over a counter).
+fn next_priority() -> u64 {
+    thread_local! {
+        static STATE: Cell<u64> = const { Cell::new(0) };
+    }
+
+    STATE.with(|state| {
+        let mut z = state.get().wrapping_add(0x9e37_79b9_7f4a_7c15);
+        state.set(z);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    })
+}
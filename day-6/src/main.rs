@@ -1,44 +1,248 @@
-use std::collections::HashSet;
-use std::io::{self, BufRead};
+use std::env;
+use std::fmt::Write as _;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::process;
+
+mod marker;
+mod scan;
+
+use marker::{find_marker, markers, packets, MarkerDetector, Packet};
+use scan::Report;
+
+const PACKET_MARKER_SIZE: usize = 4;
+const MESSAGE_MARKER_SIZE: usize = 14;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Print only the first marker (the puzzle answer).
+    First,
+    /// Print every marker.
+    All,
+    /// Split the stream into packets.
+    Packets,
+}
+
+#[derive(Debug)]
+struct Options {
+    mode: Mode,
+    json: bool,
+    /// Only for stdin; files are always scanned for both kinds of markers.
+    window_size: Option<usize>,
+    /// Files (or directories of files) to scan instead of stdin.
+    paths: Vec<PathBuf>,
+}
 
 fn main() {
+    let options = parse_args(env::args().skip(1));
+
+    if !options.paths.is_empty() {
+        if options.mode != Mode::First || options.window_size.is_some() {
+            eprintln!("--all, --packets, and --window only work on stdin, not on files");
+            process::exit(2);
+        }
+        let reports = scan::scan_paths(&options.paths);
+        print_reports(&reports, options.json);
+        return;
+    }
+
     let stdin = io::stdin();
-    let buffer = stdin.lock().lines().next().unwrap().unwrap();
-    let stream = buffer.trim();
-    let answer = start_message(stream).unwrap();
+    let window_size = options.window_size.unwrap_or(MESSAGE_MARKER_SIZE);
+    if options.mode == Mode::First {
+        match find_marker(stdin.lock(), window_size) {
+            Ok(answer) if options.json => {
+                let marker = answer.map_or("null".to_owned(), |p| p.to_string());
+                println!(r#"{{"window_size": {window_size}, "marker": {marker}}}"#);
+            }
+            Ok(answer) => println!("{}", show(answer)),
+            Err(error) => {
+                eprintln!("cannot read stdin: {error}");
+                process::exit(1);
+            }
+        }
+        return;
+    }
 
-    println!("{answer}");
-}
+    // The datastream is a single line.
+    let stream = stdin
+        .lock()
+        .bytes()
+        .map(Result::unwrap)
+        .take_while(|&byte| byte != b'\n' && byte != b'\r');
 
-pub fn start_packet(s: &str) -> Option<usize> {
-    start_distinct::<4>(s)
+    match (options.mode, options.json) {
+        (Mode::All, false) => {
+            println!("{:>6}  {:>8}", "#", "position");
+            for (i, position) in markers(stream, window_size).enumerate() {
+                println!("{:>6}  {:>8}", i + 1, position);
+            }
+        }
+        (Mode::All, true) => {
+            let positions: Vec<_> = markers(stream, window_size)
+                .map(|position| position.to_string())
+                .collect();
+            println!(
+                r#"{{"window_size": {}, "markers": [{}]}}"#,
+                window_size,
+                positions.join(", ")
+            );
+        }
+        (Mode::Packets, false) => {
+            println!(
+                "{:>6}  {:>8}  {:>17}  {:>8}",
+                "#", "start", "payload", "end"
+            );
+            for (i, packet) in packets(stream, PACKET_MARKER_SIZE, MESSAGE_MARKER_SIZE).enumerate()
+            {
+                let Packet {
+                    start,
+                    payload,
+                    end,
+                } = packet;
+                let payload = format!("{}..{}", payload.start, payload.end);
+                let end = end.map_or("-".to_owned(), |end| end.to_string());
+                println!("{:>6}  {:>8}  {:>17}  {:>8}", i + 1, start, payload, end);
+            }
+        }
+        (Mode::Packets, true) => {
+            let packets: Vec<_> = packets(stream, PACKET_MARKER_SIZE, MESSAGE_MARKER_SIZE)
+                .map(|packet| {
+                    let end = packet.end.map_or("null".to_owned(), |end| end.to_string());
+                    format!(
+                        r#"{{"start": {}, "payload": [{}, {}], "end": {}}}"#,
+                        packet.start, packet.payload.start, packet.payload.end, end
+                    )
+                })
+                .collect();
+            println!("[{}]", packets.join(", "));
+        }
+        (Mode::First, _) => unreachable!(),
+    }
 }
 
-pub fn start_message(s: &str) -> Option<usize> {
-    start_distinct::<14>(s)
+/// Usage: `day-6 [--all | --packets] [--json] [--window N] [PATH...]`
+///
+/// The window size defaults to the start-of-message marker size. When paths are given, each file
+/// (or each file in each directory) is scanned for both kinds of markers instead of reading stdin.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Options {
+    let mut options = Options {
+        mode: Mode::First,
+        json: false,
+        window_size: None,
+        paths: Vec::new(),
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--all" => options.mode = Mode::All,
+            "--packets" => options.mode = Mode::Packets,
+            "--json" => options.json = true,
+            "--window" => {
+                let window_size = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .filter(|&n| n > 0)
+                    .expect("--window needs a size of at least 1");
+                options.window_size = Some(window_size);
+            }
+            _ if arg.starts_with("--") => panic!("unknown argument {arg}"),
+            _ => options.paths.push(arg.into()),
+        }
+    }
+
+    options
 }
 
-fn start_distinct<const PREFIX_SIZE: usize>(s: &str) -> Option<usize> {
-    let mut buffer = ['\0'; PREFIX_SIZE];
+/// A marker position, or `none`.
+fn show(position: Option<usize>) -> String {
+    position.map_or("none".to_owned(), |p| p.to_string())
+}
 
-    for (i, c) in s.chars().enumerate() {
-        buffer[i % PREFIX_SIZE] = c;
-        if i < PREFIX_SIZE {
-            continue;
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if c.is_control() => write!(quoted, "\\u{:04x}", c as u32).unwrap(),
+            c => quoted.push(c),
         }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn print_reports(reports: &[Report], json: bool) {
+    if json {
+        let reports: Vec<_> = reports
+            .iter()
+            .map(|report| {
+                let path = json_string(&report.path.display().to_string());
+                match &report.result {
+                    Ok(markers) => {
+                        let json = |position: Option<usize>| {
+                            position.map_or("null".to_owned(), |p| p.to_string())
+                        };
+                        format!(
+                            r#"{{"path": {path}, "start_of_packet": {}, "start_of_message": {}}}"#,
+                            json(markers.start_of_packet),
+                            json(markers.start_of_message)
+                        )
+                    }
+                    Err(e) => format!(
+                        r#"{{"path": {path}, "error": {}}}"#,
+                        json_string(&e.to_string())
+                    ),
+                }
+            })
+            .collect();
+        println!("[{}]", reports.join(", "));
+        return;
+    }
 
-        let set: HashSet<_> = buffer.iter().collect();
-        if set.len() == PREFIX_SIZE {
-            return Some(i + 1);
+    let width = reports
+        .iter()
+        .map(|report| report.path.display().to_string().len())
+        .chain(Some("file".len()))
+        .max()
+        .unwrap();
+    println!("{:<width$}  {:>8}  {:>8}", "file", "packet", "message");
+    for report in reports {
+        let path = report.path.display();
+        match &report.result {
+            Ok(markers) => println!(
+                "{:<width$}  {:>8}  {:>8}",
+                path,
+                show(markers.start_of_packet),
+                show(markers.start_of_message)
+            ),
+            Err(e) => println!("{path:<width$}  error: {e}"),
         }
     }
+}
 
-    None
+pub fn start_packet(s: &str) -> Option<usize> {
+    start_distinct(s, PACKET_MARKER_SIZE)
+}
+
+pub fn start_message(s: &str) -> Option<usize> {
+    start_distinct(s, MESSAGE_MARKER_SIZE)
+}
+
+fn start_distinct(s: &str, window_size: usize) -> Option<usize> {
+    let mut detector = MarkerDetector::new(window_size);
+    s.bytes()
+        .position(|byte| detector.push(byte))
+        .map(|i| i + 1)
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use proptest::prelude::*;
+
     #[test]
     fn test_start_packet() {
         assert_eq!(Some(7), start_packet("mjqjpqmgbljsphdztnvjfqwrcgsmlb"));
@@ -56,4 +260,174 @@ mod test {
         assert_eq!(Some(29), start_message("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg"));
         assert_eq!(Some(26), start_message("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw"));
     }
+
+    #[test]
+    fn test_find_marker_from_reader() {
+        let stream = "mjqjpqmgbljsphdztnvjfqwrcgsmlb\n";
+        assert_eq!(Some(7), find_marker(stream.as_bytes(), 4).unwrap());
+        assert_eq!(Some(19), find_marker(stream.as_bytes(), 14).unwrap());
+        // Only the first line is the datastream:
+        assert_eq!(None, find_marker(stream.as_bytes(), 27).unwrap());
+        assert_eq!(None, find_marker("aaaa\nbcde".as_bytes(), 4).unwrap());
+    }
+
+    #[test]
+    fn test_runtime_window_sizes() {
+        let stream = "mjqjpqmgbljsphdztnvjfqwrcgsmlb";
+        assert_eq!(Some(1), start_distinct(stream, 1));
+        assert_eq!(Some(2), start_distinct(stream, 2));
+        assert_eq!(Some(3), start_distinct(stream, 3));
+        assert_eq!(None, start_distinct("aaaaaaa", 2));
+    }
+
+    #[test]
+    fn test_every_marker() {
+        let all: Vec<_> = markers("abcabcdd".bytes(), 3).collect();
+        assert_eq!(vec![3, 4, 5, 6, 7], all);
+
+        let first = markers("bvwbjplbgvbhsrlpgdmjqwftvncz".bytes(), 4).next();
+        assert_eq!(start_packet("bvwbjplbgvbhsrlpgdmjqwftvncz"), first);
+
+        assert_eq!(0, markers("aaaa".bytes(), 2).count());
+    }
+
+    #[test]
+    fn test_packets() {
+        // [abcd] zz [zwxy] qqq [qrst] uk [klmn] o
+        let stream = "abcdzzzwxyqqqqrstukklmno";
+        let segments: Vec<_> = packets(stream.bytes(), 4, 4).collect();
+        assert_eq!(
+            vec![
+                Packet {
+                    start: 4,
+                    payload: 4..6,
+                    end: Some(10),
+                },
+                Packet {
+                    start: 17,
+                    payload: 17..19,
+                    end: Some(23),
+                },
+            ],
+            segments
+        );
+
+        // A packet without a start-of-message marker runs to the end of the stream.
+        let segments: Vec<_> = packets("abcdzzzz".bytes(), 4, 4).collect();
+        assert_eq!(
+            vec![Packet {
+                start: 4,
+                payload: 4..8,
+                end: None,
+            }],
+            segments
+        );
+    }
+
+    #[test]
+    fn test_find_both_markers() {
+        let stream = "mjqjpqmgbljsphdztnvjfqwrcgsmlb\n";
+        assert_eq!(
+            vec![Some(7), Some(19)],
+            marker::find_markers(stream.as_bytes(), &[4, 14]).unwrap()
+        );
+        assert_eq!(
+            vec![Some(2), None],
+            marker::find_markers("abab".as_bytes(), &[2, 3]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_scan_files() {
+        let dir = env::temp_dir().join(format!("day-6-scan-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.txt"), "mjqjpqmgbljsphdztnvjfqwrcgsmlb\n").unwrap();
+        std::fs::write(dir.join("b.txt"), "aaaaaaaa\n").unwrap();
+
+        let reports = scan::scan_paths(&[dir.clone(), dir.join("missing.txt"), dir.join("b.txt")]);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(4, reports.len());
+        assert_eq!(dir.join("a.txt"), reports[0].path);
+        assert_eq!(dir.join("b.txt"), reports[3].path);
+        assert_eq!(
+            scan::Markers {
+                start_of_packet: Some(7),
+                start_of_message: Some(19),
+            },
+            *reports[0].result.as_ref().unwrap()
+        );
+        assert_eq!(
+            scan::Markers {
+                start_of_packet: None,
+                start_of_message: None,
+            },
+            *reports[1].result.as_ref().unwrap()
+        );
+        assert!(reports[2].result.is_err());
+    }
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(r#""plain.txt""#, json_string("plain.txt"));
+        assert_eq!(r#""say \"hi\"\\\n""#, json_string("say \"hi\"\\\n"));
+        assert_eq!(r#""\u001b[31mred""#, json_string("\x1b[31mred"));
+    }
+
+    #[test]
+    fn test_marker_in_first_window() {
+        assert_eq!(Some(4), start_packet("abcdddd"));
+        assert_eq!(Some(4), start_packet("abcd"));
+        assert_eq!(Some(14), start_message("abcdefghijklmn"));
+        assert_eq!(Some(4), find_marker("abcd\n".as_bytes(), 4).unwrap());
+        assert_eq!(Some(4), markers("abcd".bytes(), 4).next());
+    }
+
+    /// Obviously-correct (and obviously slow) reference: every position after a window of
+    /// distinct bytes.
+    fn brute_force_markers(stream: &[u8], window_size: usize) -> Vec<usize> {
+        (window_size..=stream.len())
+            .filter(|&end| {
+                let window = &stream[end - window_size..end];
+                (0..window_size).all(|i| (i + 1..window_size).all(|j| window[i] != window[j]))
+            })
+            .collect()
+    }
+
+    /// A window size, and a stream drawn from an alphabet of the first few lowercase letters.
+    fn letter_stream() -> impl Strategy<Value = (String, usize)> {
+        (1u8..=26, 1usize..=20).prop_flat_map(|(alphabet_size, window_size)| {
+            let letter = (0..alphabet_size).prop_map(|i| (b'a' + i) as char);
+            (
+                proptest::collection::vec(letter, 0..300).prop_map(String::from_iter),
+                Just(window_size),
+            )
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_first_marker_matches_brute_force((stream, window_size) in letter_stream()) {
+            let expected = brute_force_markers(stream.as_bytes(), window_size).first().copied();
+            prop_assert_eq!(expected, start_distinct(&stream, window_size));
+            prop_assert_eq!(expected, find_marker(stream.as_bytes(), window_size).unwrap());
+        }
+
+        #[test]
+        fn prop_every_marker_matches_brute_force(
+            stream in proptest::collection::vec(any::<u8>(), 0..300),
+            window_size in 1usize..=40,
+        ) {
+            let expected = brute_force_markers(&stream, window_size);
+            let actual: Vec<_> = markers(stream.iter().copied(), window_size).collect();
+            prop_assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn prop_every_letter_marker_matches_brute_force((stream, window_size) in letter_stream()) {
+            let expected = brute_force_markers(stream.as_bytes(), window_size);
+            let actual: Vec<_> = markers(stream.bytes(), window_size).collect();
+            prop_assert_eq!(expected, actual);
+        }
+    }
 }
@@ -0,0 +1,209 @@
+use std::io::{self, BufRead, BufReader, Read};
+use std::ops::Range;
+
+/// Detects markers — runs of `window` bytes that are all different — one byte at a time.
+///
+/// Instead of rebuilding a set for every byte, the detector keeps a count of how many times each
+/// byte value appears in the current window, plus the number of "extra" copies in the window. The
+/// window is all distinct exactly when it is full and there are no extra copies, so each update is
+/// O(1) no matter how large the window is.
+#[derive(Debug, Clone)]
+pub struct MarkerDetector {
+    window: Vec<u8>,
+    counts: [u32; 256],
+    duplicates: usize,
+    position: usize,
+}
+
+impl MarkerDetector {
+    pub fn new(window_size: usize) -> Self {
+        assert!(window_size > 0, "window size must be at least 1");
+
+        MarkerDetector {
+            window: vec![0; window_size],
+            counts: [0; 256],
+            duplicates: 0,
+            position: 0,
+        }
+    }
+
+    pub fn window_size(&self) -> usize {
+        self.window.len()
+    }
+
+    /// Number of bytes seen so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Feeds the next byte of the stream. Returns `true` when the last `window_size()` bytes
+    /// (including this one) are all distinct.
+    ///
+    /// That includes the very first full window: `abcd` is a marker at position 4. (The original
+    /// `start_distinct` only started checking after it, so it missed that marker.)
+    pub fn push(&mut self, byte: u8) -> bool {
+        let slot = self.position % self.window_size();
+
+        if self.position >= self.window_size() {
+            let evicted = self.window[slot];
+            self.counts[evicted as usize] -= 1;
+            if self.counts[evicted as usize] > 0 {
+                self.duplicates -= 1;
+            }
+        }
+
+        self.window[slot] = byte;
+        if self.counts[byte as usize] > 0 {
+            self.duplicates += 1;
+        }
+        self.counts[byte as usize] += 1;
+        self.position += 1;
+
+        self.position >= self.window_size() && self.duplicates == 0
+    }
+}
+
+/// Reads the datastream from `reader` until the first marker of the given size. Returns how many
+/// bytes had to be read, or `None` if the stream (or its first line) ended without a marker.
+pub fn find_marker(reader: impl Read, window_size: usize) -> io::Result<Option<usize>> {
+    Ok(find_markers(reader, &[window_size])?[0])
+}
+
+/// Like [`find_marker`], but looks for the first marker of several sizes in a single pass.
+pub fn find_markers(reader: impl Read, window_sizes: &[usize]) -> io::Result<Vec<Option<usize>>> {
+    let mut reader = BufReader::new(reader);
+    let mut detectors: Vec<_> = window_sizes
+        .iter()
+        .map(|&size| MarkerDetector::new(size))
+        .collect();
+    let mut found = vec![None; window_sizes.len()];
+
+    loop {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            return Ok(found);
+        }
+
+        let length = buffer.len();
+        for &byte in buffer {
+            // The datastream is a single line.
+            if byte == b'\n' || byte == b'\r' {
+                return Ok(found);
+            }
+
+            for (detector, found) in detectors.iter_mut().zip(found.iter_mut()) {
+                if found.is_none() && detector.push(byte) {
+                    *found = Some(detector.position());
+                }
+            }
+            if found.iter().all(Option::is_some) {
+                return Ok(found);
+            }
+        }
+        reader.consume(length);
+    }
+}
+
+/// Iterator over the position of every marker in a stream (see [`markers`]).
+#[derive(Debug, Clone)]
+pub struct Markers<I> {
+    bytes: I,
+    detector: MarkerDetector,
+}
+
+/// Yields the position after every place where the last `window_size` bytes are all distinct.
+/// The first item is the same as the answer from [`find_marker`].
+pub fn markers<I>(bytes: I, window_size: usize) -> Markers<I::IntoIter>
+where
+    I: IntoIterator<Item = u8>,
+{
+    Markers {
+        bytes: bytes.into_iter(),
+        detector: MarkerDetector::new(window_size),
+    }
+}
+
+impl<I: Iterator<Item = u8>> Iterator for Markers<I> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        for byte in self.bytes.by_ref() {
+            if self.detector.push(byte) {
+                return Some(self.detector.position());
+            }
+        }
+        None
+    }
+}
+
+/// One segment of a datastream: a start-of-packet marker, its payload, and the start-of-message
+/// marker that ends it.
+///
+/// All positions count bytes from the start of the stream, like the puzzle answers do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet {
+    /// Position right after the start-of-packet marker.
+    pub start: usize,
+    /// The bytes between the two markers.
+    pub payload: Range<usize>,
+    /// Position right after the start-of-message marker, or `None` if the stream ended first
+    /// (in which case the payload runs to the end of the stream).
+    pub end: Option<usize>,
+}
+
+/// Iterator that splits a stream into [`Packet`]s (see [`packets`]).
+#[derive(Debug, Clone)]
+pub struct Packets<I> {
+    bytes: I,
+    packet_size: usize,
+    message_size: usize,
+    /// Number of bytes consumed by the previous packets.
+    consumed: usize,
+}
+
+/// Splits a stream into packets. Each packet starts with a start-of-packet marker of
+/// `packet_size` bytes, and its payload runs up to the next start-of-message marker of
+/// `message_size` bytes.
+///
+/// Markers never overlap each other: the start-of-message marker must come entirely after the
+/// start-of-packet marker, and the next packet's marker entirely after that. That means the
+/// start-of-message marker of a packet is not necessarily the first one in the whole stream.
+pub fn packets<I>(bytes: I, packet_size: usize, message_size: usize) -> Packets<I::IntoIter>
+where
+    I: IntoIterator<Item = u8>,
+{
+    Packets {
+        bytes: bytes.into_iter(),
+        packet_size,
+        message_size,
+        consumed: 0,
+    }
+}
+
+impl<I: Iterator<Item = u8>> Iterator for Packets<I> {
+    type Item = Packet;
+
+    fn next(&mut self) -> Option<Packet> {
+        // A fresh detector only sees bytes after the previous marker, so markers can't overlap.
+        let mut detector = MarkerDetector::new(self.packet_size);
+        while !detector.push(self.bytes.next()?) {}
+        let start = self.consumed + detector.position();
+
+        let mut detector = MarkerDetector::new(self.message_size);
+        let found = loop {
+            match self.bytes.next() {
+                Some(byte) if detector.push(byte) => break true,
+                Some(_) => continue,
+                None => break false,
+            }
+        };
+        let end = start + detector.position();
+        self.consumed = end;
+
+        Some(Packet {
+            start,
+            payload: start..if found { end - self.message_size } else { end },
+            end: found.then_some(end),
+        })
+    }
+}
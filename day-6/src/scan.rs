@@ -0,0 +1,116 @@
+use std::fs::{self, File};
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::marker::find_markers;
+use crate::{MESSAGE_MARKER_SIZE, PACKET_MARKER_SIZE};
+
+/// What was found in one captured datastream file.
+#[derive(Debug)]
+pub struct Report {
+    pub path: PathBuf,
+    pub result: io::Result<Markers>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Markers {
+    pub start_of_packet: Option<usize>,
+    pub start_of_message: Option<usize>,
+}
+
+/// Scans every file in `paths`, and every file directly inside each directory in `paths`, in
+/// order. A directory that can't be listed gets a report of its own.
+pub fn scan_paths(paths: &[PathBuf]) -> Vec<Report> {
+    let mut files = Vec::new();
+    // Where each unreadable directory goes among the reports for the files.
+    let mut unreadable = Vec::new();
+    for path in paths {
+        match expand_directory(path) {
+            Ok(expanded) => files.extend(expanded),
+            Err(error) => unreadable.push((
+                files.len(),
+                Report {
+                    path: path.clone(),
+                    result: Err(error),
+                },
+            )),
+        }
+    }
+
+    let mut reports = scan_files(&files);
+    for (i, report) in unreadable.into_iter().rev() {
+        reports.insert(i, report);
+    }
+    reports
+}
+
+/// The files directly inside `path`, sorted by name, if it's a directory; otherwise, just `path`.
+fn expand_directory(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_owned()]);
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Scans every file on a small pool of worker threads. The reports are in the same order as
+/// `paths`.
+fn scan_files(paths: &[PathBuf]) -> Vec<Report> {
+    let n_threads = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(paths.len());
+    let next_path = AtomicUsize::new(0);
+
+    let mut results: Vec<_> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..n_threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next_path.fetch_add(1, Ordering::Relaxed);
+                        let Some(path) = paths.get(i) else {
+                            break;
+                        };
+                        done.push((i, scan_file(path)));
+                    }
+                    done
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("worker thread panicked"))
+            .collect()
+    });
+
+    results.sort_by_key(|&(i, _)| i);
+    results
+        .into_iter()
+        .zip(paths)
+        .map(|((_, result), path)| Report {
+            path: path.clone(),
+            result,
+        })
+        .collect()
+}
+
+fn scan_file(path: &Path) -> io::Result<Markers> {
+    let file = File::open(path)?;
+    let found = find_markers(file, &[PACKET_MARKER_SIZE, MESSAGE_MARKER_SIZE])?;
+    Ok(Markers {
+        start_of_packet: found[0],
+        start_of_message: found[1],
+    })
+}
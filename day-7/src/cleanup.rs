@@ -0,0 +1,264 @@
+use std::collections::BTreeMap;
+
+use crate::fs::{FsTree, NodeId, NodeKind};
+
+/// Beyond this many partial sums in a subtree, sums that are close together get merged, so the
+/// answer might be a little bigger than the best possible.
+const MAX_STATES: usize = 512;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cleanup {
+    /// Directories to delete; none of them is inside another, and none of them is `/`.
+    pub directories: Vec<NodeId>,
+    pub reclaimed: usize,
+    pub method: Method,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    /// The least space that can possibly be reclaimed.
+    Exact,
+    /// At most `within` bytes more than the least that can possibly be reclaimed.
+    Approximate { within: usize },
+}
+
+/// Picks directories to delete so that at least `needed` bytes are reclaimed, deleting as little
+/// as possible. Returns `None` if even deleting every directory won't do. `/` itself is never
+/// suggested.
+///
+/// This is a knapsack problem over the tree: each directory can either be deleted as a whole, or
+/// any combination of its subdirectories can be.
+pub fn plan_cleanup(fs: &FsTree, needed: usize) -> Option<Cleanup> {
+    let sizes = fs.sizes();
+    let (guess, guessed) = greedy(fs, &sizes, needed)?;
+
+    let mut plan = search(fs, &sizes, needed);
+    if guessed < plan.reclaimed {
+        // Only possible when the search had to merge sums; the bound still holds.
+        plan.directories = guess;
+        plan.reclaimed = guessed;
+    }
+    plan.directories.sort_by_key(|&dir| fs.path(dir));
+    Some(plan)
+}
+
+/// Which directories were picked, as a tree of joins so that combining two choices is O(1).
+/// Choices live in an arena and refer to each other by index.
+#[derive(Debug, Clone, Copy)]
+enum Choice {
+    Nothing,
+    Delete(NodeId),
+    Both(usize, usize),
+}
+
+/// One way of reclaiming space in a subtree.
+///
+/// After merging, a state stands in for other ways of reclaiming space that weren't kept: each of
+/// them reclaims at least `reclaimed - slack`, and either at most `reclaimed`, or is enough already
+/// and at least as much as this.
+#[derive(Debug, Clone, Copy)]
+struct State {
+    reclaimed: usize,
+    slack: usize,
+    choice: usize,
+}
+
+/// Possible amounts of reclaimed space in a subtree, by amount.
+type States = BTreeMap<usize, State>;
+
+/// Dynamic programming over the directories, children before parents. Only call this when
+/// there's some way to reclaim enough.
+fn search(fs: &FsTree, sizes: &[usize], needed: usize) -> Cleanup {
+    let mut states: BTreeMap<NodeId, States> = BTreeMap::new();
+    let mut choices = vec![Choice::Nothing];
+    let nothing = State {
+        reclaimed: 0,
+        slack: 0,
+        choice: 0,
+    };
+
+    for dir in post_order(fs) {
+        let NodeKind::Directory(children) = &fs.node(dir).kind else {
+            unreachable!("only directories are visited");
+        };
+
+        // Leave this directory alone, and delete any combination inside of it...
+        let mut combined = States::from([(0, nothing)]);
+        for child in children.values() {
+            let Some(child_states) = states.remove(child) else {
+                continue;
+            };
+
+            let mut next = States::new();
+            for a in combined.values() {
+                for b in child_states.values() {
+                    let state = State {
+                        reclaimed: a.reclaimed + b.reclaimed,
+                        slack: a.slack + b.slack,
+                        choice: 0,
+                    };
+                    add(&mut next, state, || {
+                        choices.push(Choice::Both(a.choice, b.choice));
+                        choices.len() - 1
+                    });
+                }
+            }
+            tidy(&mut next, needed);
+            combined = next;
+        }
+
+        // ...or delete the whole thing, unless it's the root.
+        if dir != FsTree::ROOT {
+            let state = State {
+                reclaimed: sizes[dir],
+                slack: 0,
+                choice: 0,
+            };
+            add(&mut combined, state, || {
+                choices.push(Choice::Delete(dir));
+                choices.len() - 1
+            });
+            tidy(&mut combined, needed);
+        }
+        states.insert(dir, combined);
+    }
+
+    let root_states = states
+        .remove(&FsTree::ROOT)
+        .expect("the root is visited last");
+    let (_, best) = root_states
+        .range(needed..)
+        .next()
+        .expect("some cleanup is enough");
+    Cleanup {
+        directories: flatten(&choices, best.choice),
+        reclaimed: best.reclaimed,
+        method: match best.slack {
+            0 => Method::Exact,
+            within => Method::Approximate { within },
+        },
+    }
+}
+
+/// Adds a state, unless there's already one that reclaims the same amount.
+fn add(states: &mut States, state: State, choice: impl FnOnce() -> usize) {
+    states
+        .entry(state.reclaimed)
+        .and_modify(|existing| existing.slack = existing.slack.max(state.slack))
+        .or_insert_with(|| State {
+            choice: choice(),
+            ..state
+        });
+}
+
+/// Keeps only the smallest way of reclaiming enough, and if there are still too many ways of
+/// reclaiming too little, merges the ones that are close together.
+fn tidy(states: &mut States, needed: usize) {
+    let enough = states.split_off(&needed);
+    let mut enough = enough.into_values();
+    if let Some(mut smallest) = enough.next() {
+        smallest.slack = enough.fold(smallest.slack, |slack, other| slack.max(other.slack));
+        states.insert(smallest.reclaimed, smallest);
+    }
+
+    if states.len() <= MAX_STATES {
+        return;
+    }
+
+    // Keep the biggest amount in each bucket, since the others can't reclaim more than it.
+    let bucket_size = needed.div_ceil(MAX_STATES);
+    let mut merged = States::new();
+    let mut last_bucket = None;
+    for state in states.values().rev() {
+        let bucket = state.reclaimed / bucket_size;
+        match merged.values_mut().next() {
+            Some(kept) if last_bucket == Some(bucket) && kept.reclaimed < needed => {
+                let slack = state.slack + (kept.reclaimed - state.reclaimed);
+                kept.slack = kept.slack.max(slack);
+            }
+            _ => {
+                merged.insert(state.reclaimed, *state);
+                last_bucket = Some(bucket).filter(|_| state.reclaimed < needed);
+            }
+        }
+    }
+    *states = merged;
+}
+
+/// Takes the smallest directory that covers what's still needed; failing that, the biggest
+/// directory that doesn't overlap what was already picked, and keeps going.
+///
+/// Biggest first means every directory is picked before anything inside of it, so this only gives
+/// up once every subdirectory of `/` has been picked. Returns the directories and how much they
+/// reclaim.
+fn greedy(fs: &FsTree, sizes: &[usize], needed: usize) -> Option<(Vec<NodeId>, usize)> {
+    let mut by_size: Vec<_> = fs
+        .directories()
+        .filter(|&dir| dir != FsTree::ROOT)
+        .map(|dir| (sizes[dir], dir))
+        .collect();
+    by_size.sort();
+
+    let mut directories: Vec<NodeId> = Vec::new();
+    let mut reclaimed = 0;
+    let overlaps = |picked: &[NodeId], dir: NodeId| {
+        picked
+            .iter()
+            .any(|&other| is_inside(fs, dir, other) || is_inside(fs, other, dir))
+    };
+
+    while reclaimed < needed {
+        let still_needed = needed - reclaimed;
+        let mut candidates = by_size
+            .iter()
+            .filter(|&&(_, dir)| !overlaps(&directories, dir));
+        let covering = candidates.clone().find(|&&(size, _)| size >= still_needed);
+        let &(size, dir) = covering.or_else(|| candidates.next_back())?;
+        directories.push(dir);
+        reclaimed += size;
+    }
+
+    Some((directories, reclaimed))
+}
+
+fn flatten(choices: &[Choice], choice: usize) -> Vec<NodeId> {
+    let mut directories = Vec::new();
+    let mut pending = vec![choice];
+    while let Some(choice) = pending.pop() {
+        match choices[choice] {
+            Choice::Nothing => (),
+            Choice::Delete(dir) => directories.push(dir),
+            Choice::Both(a, b) => {
+                pending.push(a);
+                pending.push(b);
+            }
+        }
+    }
+    directories
+}
+
+/// Whether `node` is `dir` or somewhere below it.
+fn is_inside(fs: &FsTree, node: NodeId, dir: NodeId) -> bool {
+    let mut current = Some(node);
+    while let Some(id) = current {
+        if id == dir {
+            return true;
+        }
+        current = fs.node(id).parent;
+    }
+    false
+}
+
+/// Directories, with every directory coming after all of its subdirectories.
+fn post_order(fs: &FsTree) -> Vec<NodeId> {
+    let mut order = Vec::new();
+    let mut pending = vec![FsTree::ROOT];
+    while let Some(dir) = pending.pop() {
+        order.push(dir);
+        if let NodeKind::Directory(children) = &fs.node(dir).kind {
+            pending.extend(children.values().filter(|&&child| fs.is_directory(child)));
+        }
+    }
+    order.reverse();
+    order
+}
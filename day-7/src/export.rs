@@ -0,0 +1,150 @@
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::fs::{FsTree, NodeId, NodeKind};
+
+/// Nested JSON, one object per entry:
+///
+/// ```json
+/// {"name": "/", "kind": "dir", "size": 584, "children": [
+///   {"name": "i", "kind": "file", "size": 584}
+/// ]}
+/// ```
+pub fn to_json(fs: &FsTree) -> String {
+    enum Step {
+        Open(NodeId),
+        Close,
+    }
+
+    let sizes = fs.sizes();
+    let mut output = String::new();
+    let mut pending = vec![Step::Open(FsTree::ROOT)];
+    // Whether the next entry is the first in its directory (and so needs no comma).
+    let mut first = true;
+
+    while let Some(step) = pending.pop() {
+        let id = match step {
+            Step::Open(id) => id,
+            Step::Close => {
+                output.push_str("]}");
+                first = false;
+                continue;
+            }
+        };
+
+        if !first {
+            output.push_str(", ");
+        }
+        let node = fs.node(id);
+        let name = json_string(&node.name);
+        match &node.kind {
+            NodeKind::File(size) => {
+                write!(
+                    output,
+                    r#"{{"name": {name}, "kind": "file", "size": {size}}}"#
+                )
+                .unwrap();
+                first = false;
+            }
+            NodeKind::Directory(children) => {
+                let size = sizes[id];
+                write!(
+                    output,
+                    r#"{{"name": {name}, "kind": "dir", "size": {size}, "children": ["#
+                )
+                .unwrap();
+                pending.push(Step::Close);
+                pending.extend(children.values().rev().map(|&child| Step::Open(child)));
+                first = true;
+            }
+        }
+    }
+
+    output
+}
+
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if c.is_control() => write!(quoted, "\\u{:04x}", c as u32).unwrap(),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Recreates the tree in a new directory inside `parent`, and returns where. Files are sparse:
+/// they have the recorded size, but (on most filesystems) take up no space.
+pub fn materialize(fs: &FsTree, parent: &Path) -> io::Result<PathBuf> {
+    fs::create_dir_all(parent)?;
+    let destination = fresh_directory(parent)?;
+
+    for (id, _) in fs.walk(None).skip(1) {
+        let path = disk_path(fs, id, &destination)?;
+        match fs.node(id).kind {
+            NodeKind::Directory(_) => fs::create_dir(&path)?,
+            NodeKind::File(size) => File::create(&path)?.set_len(size as u64)?,
+        }
+    }
+
+    Ok(destination)
+}
+
+/// Makes a new, empty `tree-N` directory inside `parent`, so that earlier runs are left alone.
+fn fresh_directory(parent: &Path) -> io::Result<PathBuf> {
+    let mut n = 0;
+    loop {
+        let path = parent.join(format!("tree-{n}"));
+        match fs::create_dir(&path) {
+            Ok(()) => return Ok(path),
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => n += 1,
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+/// Where a node goes on disk. Refuses names that would escape `destination`.
+fn disk_path(fs: &FsTree, id: NodeId, destination: &Path) -> io::Result<PathBuf> {
+    let mut path = destination.to_owned();
+    let absolute = fs.path(id);
+    for name in absolute.split('/').skip(1) {
+        if name.is_empty() || name == "." || name == ".." || name.contains('\0') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("refusing to create {absolute:?}"),
+            ));
+        }
+        path.push(name);
+    }
+    Ok(path)
+}
+
+/// Reads a real directory tree back, with file sizes taken from their metadata.
+pub fn read_from_disk(source: &Path) -> io::Result<FsTree> {
+    let mut tree = FsTree::new();
+    let mut pending = vec![(source.to_owned(), FsTree::ROOT)];
+
+    while let Some((path, dir)) = pending.pop() {
+        for entry in fs::read_dir(&path)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                let child = tree.add_directory(dir, &name);
+                pending.push((entry.path(), child));
+            } else if file_type.is_file() {
+                tree.add_file(dir, &name, entry.metadata()?.len() as usize);
+            }
+        }
+    }
+
+    Ok(tree)
+}
@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+
+pub type NodeId = usize;
+
+/// The filesystem as seen through the terminal transcript.
+///
+/// Every directory and file lives in one arena; nodes refer to each other by index. Sizes of
+/// directories are not stored -- they're computed from the files whenever they're asked for.
+#[derive(Debug, Clone)]
+pub struct FsTree {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub name: String,
+    pub parent: Option<NodeId>,
+    pub kind: NodeKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum NodeKind {
+    /// Children are kept sorted by name.
+    Directory(BTreeMap<String, NodeId>),
+    File(usize),
+}
+
+impl FsTree {
+    pub const ROOT: NodeId = 0;
+
+    pub fn new() -> Self {
+        FsTree {
+            nodes: vec![Node {
+                name: "/".to_owned(),
+                parent: None,
+                kind: NodeKind::Directory(BTreeMap::new()),
+            }],
+        }
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id]
+    }
+
+    pub fn is_directory(&self, id: NodeId) -> bool {
+        matches!(self.nodes[id].kind, NodeKind::Directory(_))
+    }
+
+    /// Finds the entry called `name` inside the directory `dir`.
+    pub fn child(&self, dir: NodeId, name: &str) -> Option<NodeId> {
+        match &self.nodes[dir].kind {
+            NodeKind::Directory(children) => children.get(name).copied(),
+            NodeKind::File(_) => None,
+        }
+    }
+
+    /// Adds a directory, or returns the existing one if it's already there.
+    pub fn add_directory(&mut self, parent: NodeId, name: &str) -> NodeId {
+        self.add_node(parent, name, NodeKind::Directory(BTreeMap::new()))
+    }
+
+    /// Adds a file, or returns the existing one if it's already there.
+    pub fn add_file(&mut self, parent: NodeId, name: &str, size: usize) -> NodeId {
+        self.add_node(parent, name, NodeKind::File(size))
+    }
+
+    pub fn set_file_size(&mut self, file: NodeId, size: usize) {
+        let NodeKind::File(old_size) = &mut self.nodes[file].kind else {
+            panic!("{} is not a file", self.path(file));
+        };
+        *old_size = size;
+    }
+
+    fn add_node(&mut self, parent: NodeId, name: &str, kind: NodeKind) -> NodeId {
+        if let Some(existing) = self.child(parent, name) {
+            return existing;
+        }
+
+        let id = self.nodes.len();
+        self.nodes.push(Node {
+            name: name.to_owned(),
+            parent: Some(parent),
+            kind,
+        });
+        let NodeKind::Directory(children) = &mut self.nodes[parent].kind else {
+            panic!("{} is not a directory", self.path(parent));
+        };
+        children.insert(name.to_owned(), id);
+        id
+    }
+
+    /// Total size of a file, or of everything (recursively) inside a directory.
+    pub fn size(&self, id: NodeId) -> usize {
+        let mut total = 0;
+        let mut pending = vec![id];
+        while let Some(id) = pending.pop() {
+            match &self.nodes[id].kind {
+                NodeKind::File(size) => total += size,
+                NodeKind::Directory(children) => pending.extend(children.values()),
+            }
+        }
+        total
+    }
+
+    /// The size of every node at once, indexed by [`NodeId`].
+    ///
+    /// Much cheaper than calling [`FsTree::size`] on each directory, since each node is only
+    /// visited once.
+    pub fn sizes(&self) -> Vec<usize> {
+        let mut sizes: Vec<_> = self
+            .nodes
+            .iter()
+            .map(|node| match node.kind {
+                NodeKind::File(size) => size,
+                NodeKind::Directory(_) => 0,
+            })
+            .collect();
+
+        // Nodes are always added after their parent, so going backwards visits every child
+        // before its parent.
+        for id in (1..self.nodes.len()).rev() {
+            let parent = self.nodes[id].parent.expect("only the root has no parent");
+            sizes[parent] += sizes[id];
+        }
+
+        sizes
+    }
+
+    /// The absolute path of a node, e.g., `/a/e`.
+    pub fn path(&self, id: NodeId) -> String {
+        let mut names = Vec::new();
+        let mut current = id;
+        while let Some(parent) = self.nodes[current].parent {
+            names.push(self.nodes[current].name.as_str());
+            current = parent;
+        }
+        names.reverse();
+        format!("/{}", names.join("/"))
+    }
+
+    /// Finds a node by its absolute path, e.g., `/a/e`.
+    pub fn lookup(&self, path: &str) -> Option<NodeId> {
+        let relative = path.strip_prefix('/')?;
+        relative
+            .split('/')
+            .filter(|name| !name.is_empty())
+            .try_fold(FsTree::ROOT, |dir, name| self.child(dir, name))
+    }
+
+    /// Depth-first walk from the root, in the order the entries are listed. Yields each node with
+    /// its depth; entries deeper than `max_depth` (the root is at depth 0) are skipped.
+    pub fn walk(&self, max_depth: Option<usize>) -> impl Iterator<Item = (NodeId, usize)> + '_ {
+        let mut pending = vec![(FsTree::ROOT, 0)];
+
+        std::iter::from_fn(move || {
+            let (id, depth) = pending.pop()?;
+            let within_depth = max_depth.is_none_or(|max_depth| depth < max_depth);
+            if let (NodeKind::Directory(children), true) = (&self.nodes[id].kind, within_depth) {
+                pending.extend(children.values().rev().map(|&child| (child, depth + 1)));
+            }
+            Some((id, depth))
+        })
+    }
+
+    /// Every directory in the tree, including the root.
+    pub fn directories(&self) -> impl Iterator<Item = NodeId> + '_ {
+        (0..self.nodes.len()).filter(|&id| self.is_directory(id))
+    }
+}
+
+impl Default for FsTree {
+    fn default() -> Self {
+        FsTree::new()
+    }
+}
@@ -1,8 +1,19 @@
 use inpt::{inpt, Inpt};
-use std::collections::VecDeque;
+use std::env;
 use std::io::{self, BufRead};
+use std::path::PathBuf;
+use std::process;
 
-#[derive(Inpt, Debug)]
+mod cleanup;
+mod export;
+mod fs;
+mod shell;
+mod view;
+
+use fs::FsTree;
+use shell::Interpretation;
+
+#[derive(Inpt, Debug, Clone)]
 enum Line {
     #[inpt(regex = r"[$]\s+cd\s+(.+)")]
     Cd(String),
@@ -10,21 +21,136 @@ enum Line {
     Ls,
     #[inpt(regex = r"dir\s+(.+)")]
     Directory(String),
-    #[inpt(regex = r"(\d+)\s+.+")]
-    File(usize),
+    #[inpt(regex = r"(\d+)\s+(.+)")]
+    File(usize, String),
+    /// Anything else, which the interpreter warns about and skips.
+    #[inpt(regex = r"(.*)")]
+    Unparseable(String),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum View {
+    Tree,
+    Du,
 }
 
+#[derive(Debug)]
+struct Options {
+    view: Option<View>,
+    max_depth: Option<usize>,
+    paths: Vec<String>,
+    disk_size: usize,
+    required_space: usize,
+    cleanup: bool,
+    json: bool,
+    materialize: Option<PathBuf>,
+}
+
+/// Usage: `day-7 [--tree | --du | --cleanup | --json] [--max-depth N] [--disk-size N]
+/// [--required N] [--materialize DIR] [PATH...]`
+///
+/// Without arguments, prints the answers to both parts. `--tree` and `--du` show the whole
+/// filesystem, `--json` exports it, `--materialize` recreates it on disk (with sparse files, in a
+/// new `tree-N` directory inside DIR), and `--cleanup` suggests which directories to delete to get
+/// the required free space; otherwise, prints the size of each given absolute path.
 fn main() {
+    let options = parse_args(env::args().skip(1));
+
     let stdin = io::stdin();
-    let mut lines: VecDeque<_> = stdin
+    let lines: Vec<_> = stdin
         .lock()
         .lines()
-        .filter_map(Result::ok)
-        .map(|line| inpt::<Line>(line.trim()).unwrap())
+        .map_while(Result::ok)
+        .map(|line| inpt::<Line>(line.trim()).expect("anything parses as Unparseable"))
         .collect();
 
-    lines.pop_front();
-    let Calculation(_, root_size, mut dirs) = calculate_size(lines, vec![]);
+    if let Some(view) = options.view {
+        let tree = read_transcript(lines);
+        let output = match view {
+            View::Tree => view::tree(&tree, options.max_depth),
+            View::Du => view::du(&tree, options.max_depth),
+        };
+        print!("{output}");
+        return;
+    }
+
+    if options.json || options.materialize.is_some() {
+        let tree = read_transcript(lines);
+        if let Some(parent) = options.materialize {
+            // Make sure the filesystem recorded every size faithfully.
+            let round_trip = export::materialize(&tree, &parent).and_then(|destination| {
+                let from_disk = export::read_from_disk(&destination)?;
+                Ok((destination, from_disk))
+            });
+            match round_trip {
+                Ok((destination, from_disk)) => {
+                    eprintln!("materialized in {}", destination.display());
+                    if view::tree(&from_disk, None) != view::tree(&tree, None) {
+                        eprintln!(
+                            "warning: {} does not match the transcript",
+                            destination.display()
+                        );
+                    }
+                }
+                Err(error) => {
+                    eprintln!("cannot materialize in {}: {error}", parent.display());
+                    process::exit(1);
+                }
+            }
+        }
+        if options.json {
+            println!("{}", export::to_json(&tree));
+        }
+        return;
+    }
+
+    let used_space = |tree: &FsTree| tree.size(FsTree::ROOT);
+    if options.cleanup {
+        let tree = read_transcript(lines);
+        let unused_space = options.disk_size.saturating_sub(used_space(&tree));
+        let Some(needed) = options
+            .required_space
+            .checked_sub(unused_space)
+            .filter(|&n| n > 0)
+        else {
+            println!("No cleanup needed: {unused_space} already free");
+            return;
+        };
+
+        match cleanup::plan_cleanup(&tree, needed) {
+            Some(plan) => {
+                let sizes = tree.sizes();
+                for &dir in &plan.directories {
+                    println!("{}\t{}", sizes[dir], tree.path(dir));
+                }
+                let method = match plan.method {
+                    cleanup::Method::Exact => "best possible".to_owned(),
+                    cleanup::Method::Approximate { within } => {
+                        format!("at most {within} more than the best possible")
+                    }
+                };
+                println!("Reclaimed {} of {needed} needed ({method})", plan.reclaimed);
+            }
+            None => println!("Cannot free {needed}: deleting every directory is not enough"),
+        }
+        return;
+    }
+
+    if !options.paths.is_empty() {
+        let tree = read_transcript(lines);
+        for path in options.paths {
+            match tree.lookup(&path) {
+                Some(id) => {
+                    let kind = if tree.is_directory(id) { "dir" } else { "file" };
+                    println!("{} ({kind}): {}", tree.path(id), tree.size(id));
+                }
+                None => println!("{path}: not found"),
+            }
+        }
+        return;
+    }
+
+    let Calculation(root_size, mut dirs) = calculate_size(lines, vec![]);
 
     let total_size = dirs
         .iter()
@@ -33,59 +159,102 @@ fn main() {
         .sum::<usize>();
     println!("{total_size}");
 
-    let space_available = 70000000;
-    let space_required = 30000000;
-
-    let unused_space = space_available - root_size;
-    assert!(unused_space < space_required);
+    let unused_space = options.disk_size.saturating_sub(root_size);
+    if unused_space >= options.required_space {
+        println!("No cleanup needed: {unused_space} already free");
+        return;
+    }
 
     dirs.sort_by_key(|DirectorySize(_, size)| *size);
 
-    let DirectorySize(name, size) = dirs
+    match dirs
         .into_iter()
-        .find(|DirectorySize(_, size)| size + unused_space > space_required)
-        .unwrap();
-
-    println!("{name}: {size}");
+        .find(|DirectorySize(_, size)| size + unused_space > options.required_space)
+    {
+        Some(DirectorySize(name, size)) => println!("{name}: {size}"),
+        None => println!("No single directory frees up enough space; try --cleanup"),
+    }
 }
 
-struct DirectorySize(String, usize);
-struct Calculation(VecDeque<Line>, usize, Vec<DirectorySize>);
-
-fn calculate_size(mut lines: VecDeque<Line>, mut dirs: Vec<DirectorySize>) -> Calculation {
-    let Some(Line::Ls) = lines.pop_front() else {
-        panic!("Did not see ls");
+fn parse_args(mut args: impl Iterator<Item = String>) -> Options {
+    let mut options = Options {
+        view: None,
+        max_depth: None,
+        paths: Vec::new(),
+        disk_size: 70_000_000,
+        required_space: 30_000_000,
+        cleanup: false,
+        json: false,
+        materialize: None,
     };
 
-    let mut current_size = 0;
-    loop {
-        let line = lines.pop_front();
-        match line {
-            Some(Line::Cd(dir)) if &dir == ".." => {
-                return Calculation(lines, current_size, dirs);
-            }
-            Some(Line::File(size)) => current_size += size,
-            Some(Line::Directory(_)) => {
-                // Don't need to do anything for a directory
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tree" => options.view = Some(View::Tree),
+            "--du" => options.view = Some(View::Du),
+            "--cleanup" => options.cleanup = true,
+            "--json" => options.json = true,
+            "--materialize" => {
+                let destination = args.next().expect("--materialize needs a directory");
+                options.materialize = Some(destination.into());
             }
-            Some(Line::Cd(name)) => {
-                let Calculation(new_lines, size, new_dirs) = calculate_size(lines, dirs);
-                lines = new_lines;
-                dirs = new_dirs;
-                current_size += size;
-                dirs.push(DirectorySize(name, size));
-            }
-            None => {
-                return Calculation(lines, current_size, dirs);
-            }
-            _ => unimplemented!("{line:?}, {lines:?}"),
+            "--max-depth" => options.max_depth = Some(number(&mut args, &arg)),
+            "--disk-size" => options.disk_size = number(&mut args, &arg),
+            "--required" => options.required_space = number(&mut args, &arg),
+            _ => options.paths.push(arg),
         }
     }
+
+    options
+}
+
+fn number(args: &mut impl Iterator<Item = String>, flag: &str) -> usize {
+    let value = args.next().and_then(|value| value.parse().ok());
+    value.unwrap_or_else(|| panic!("{flag} needs a number"))
+}
+
+struct DirectorySize(String, usize);
+struct Calculation(usize, Vec<DirectorySize>);
+
+/// Replays the transcript, then measures every directory. Returns the size of `/`, with every
+/// other directory appended to `dirs`.
+///
+/// Nothing here recurses: the interpreter keeps its own working directory stack, and all the
+/// sizes are added up in one pass over the tree, so arbitrarily deep transcripts are fine.
+fn calculate_size(
+    lines: impl IntoIterator<Item = Line>,
+    mut dirs: Vec<DirectorySize>,
+) -> Calculation {
+    let tree = read_transcript(lines);
+    let sizes = tree.sizes();
+
+    dirs.extend(
+        tree.directories()
+            .filter(|&id| id != FsTree::ROOT)
+            .map(|id| DirectorySize(tree.node(id).name.clone(), sizes[id])),
+    );
+
+    Calculation(sizes[FsTree::ROOT], dirs)
+}
+
+/// Rebuilds the filesystem, reporting anything odd in the transcript on stderr.
+fn read_transcript(lines: impl IntoIterator<Item = Line>) -> FsTree {
+    let Interpretation { tree, warnings } = shell::interpret(lines);
+    for warning in warnings {
+        eprintln!("warning: {warning}");
+    }
+    tree
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use shell::{Warning, WarningKind};
+    use std::collections::VecDeque;
+
+    fn parse(lines: Vec<&str>) -> impl Iterator<Item = Line> + '_ {
+        lines.into_iter().map(|line| inpt::<Line>(line).unwrap())
+    }
 
     #[test]
     fn test_parse_lines() {
@@ -106,7 +275,10 @@ mod test {
         }
 
         match inpt::<Line>("62596 h.lst") {
-            Ok(File(size)) => assert_eq!(62596, size),
+            Ok(File(size, name)) => {
+                assert_eq!(62596, size);
+                assert_eq!("h.lst", &name);
+            }
             err => panic!("{err:?}"),
         }
     }
@@ -118,9 +290,8 @@ mod test {
             .map(|line| inpt::<Line>(line).unwrap())
             .collect();
 
-        let Calculation(lines, size, dirs) = calculate_size(lines, vec![]);
+        let Calculation(size, dirs) = calculate_size(lines, vec![]);
         assert_eq!(584, size);
-        assert_eq!(0, lines.len());
         assert_eq!(0, dirs.len());
     }
 
@@ -143,9 +314,8 @@ mod test {
             .map(|line| inpt::<Line>(line).unwrap())
             .collect();
 
-        let Calculation(lines, size, mut dirs) = calculate_size(lines, vec![]);
+        let Calculation(size, mut dirs) = calculate_size(lines, vec![]);
         assert_eq!(94853, size);
-        assert_eq!(0, lines.len());
         assert_eq!(1, dirs.len());
         let DirectorySize(name, e_size) = dirs.pop().unwrap();
         assert_eq!("e", &name);
@@ -185,8 +355,7 @@ mod test {
             .collect();
 
         lines.pop_front();
-        let Calculation(lines, size, mut dirs) = calculate_size(lines, vec![]);
-        assert_eq!(0, lines.len());
+        let Calculation(size, mut dirs) = calculate_size(lines, vec![]);
         assert_eq!(48381165, size);
         assert_eq!(3, dirs.len());
 
@@ -213,4 +382,483 @@ mod test {
         assert_eq!(24933642, size);
         assert_eq!("d", &name);
     }
+
+    #[test]
+    fn test_fs_tree() {
+        let lines = vec![
+            "$ cd /",
+            "$ ls",
+            "dir a",
+            "14848514 b.txt",
+            "8504156 c.dat",
+            "dir d",
+            "$ cd a",
+            "$ ls",
+            "dir e",
+            "29116 f",
+            "2557 g",
+            "62596 h.lst",
+            "$ cd e",
+            "$ ls",
+            "584 i",
+            "$ cd ..",
+            "$ cd ..",
+            "$ cd d",
+            "$ ls",
+            "4060174 j",
+            "8033020 d.log",
+            "5626152 d.ext",
+            "7214296 k",
+        ];
+        let tree = read_transcript(parse(lines));
+
+        assert_eq!(48381165, tree.size(FsTree::ROOT));
+        assert_eq!(Some(FsTree::ROOT), tree.lookup("/"));
+
+        let e = tree.lookup("/a/e").unwrap();
+        assert_eq!(584, tree.size(e));
+        assert_eq!("/a/e", tree.path(e));
+        assert!(tree.is_directory(e));
+        assert_eq!(94853, tree.size(tree.lookup("/a/").unwrap()));
+        assert_eq!(24933642, tree.size(tree.lookup("/d").unwrap()));
+
+        let h = tree.lookup("/a/h.lst").unwrap();
+        assert_eq!(62596, tree.size(h));
+        assert!(!tree.is_directory(h));
+
+        assert_eq!(None, tree.lookup("/a/nope"));
+        assert_eq!(None, tree.lookup("/a/f/i"));
+        assert_eq!(None, tree.lookup("a"));
+    }
+
+    #[test]
+    fn test_same_name_in_different_places() {
+        let lines = vec![
+            "$ cd /", "$ ls", "dir a", "dir b", "$ cd a", "$ ls", "dir x", "$ cd x", "$ ls",
+            "10 f", "$ cd ..", "$ cd ..", "$ cd b", "$ ls", "dir x", "$ cd x", "$ ls", "20 f",
+        ];
+        let tree = read_transcript(parse(lines));
+
+        assert_eq!(10, tree.size(tree.lookup("/a/x").unwrap()));
+        assert_eq!(20, tree.size(tree.lookup("/b/x").unwrap()));
+        assert_eq!(20, tree.size(tree.lookup("/b/x/f").unwrap()));
+        assert_eq!(30, tree.size(FsTree::ROOT));
+    }
+
+    #[test]
+    fn test_messy_transcript() {
+        let lines = vec![
+            "$ cd /", "$ ls", "dir a", "100 b", "$ cd a", "$ ls", "10 c",
+            // Go straight back to the root, and list it again:
+            "$ cd /", "$ ls", "dir a", "100 b", // Never listed, but it's there:
+            "$ cd x", "$ cd y", "$ ls", "1 z", "$ cd ..", "$ cd ..", "$ cd ..", "$ cd ..",
+            // Listing /a again, out of order:
+            "$ cd a", "$ ls", "10 c", "20 d",
+        ];
+        let Interpretation { tree, warnings } = shell::interpret(parse(lines));
+
+        assert_eq!(131, tree.size(FsTree::ROOT));
+        assert_eq!(30, tree.size(tree.lookup("/a").unwrap()));
+        assert_eq!(1, tree.size(tree.lookup("/x").unwrap()));
+        assert_eq!(1, tree.size(tree.lookup("/x/y/z").unwrap()));
+        assert_eq!(
+            vec![
+                Warning {
+                    line: 12,
+                    kind: WarningKind::CdIntoUnlisted("x".to_owned()),
+                },
+                Warning {
+                    line: 13,
+                    kind: WarningKind::CdIntoUnlisted("y".to_owned()),
+                },
+                Warning {
+                    line: 18,
+                    kind: WarningKind::CdAboveRoot,
+                },
+                Warning {
+                    line: 19,
+                    kind: WarningKind::CdAboveRoot,
+                },
+            ],
+            warnings
+        );
+
+        let Calculation(size, dirs) = calculate_size(
+            parse(vec![
+                "$ ls", "dir a", "$ cd a", "$ ls", "5 f", "$ cd /", "$ ls", "dir a",
+            ]),
+            vec![],
+        );
+        assert_eq!(5, size);
+        assert_eq!(1, dirs.len());
+    }
+
+    #[test]
+    fn test_suspicious_listings() {
+        let lines = vec![
+            "$ cd /", "5 f", "$ ls", "dir f", "7 f", "$ cd f", "", "$ pwd", "3 g",
+        ];
+        let Interpretation { tree, warnings } = shell::interpret(parse(lines));
+
+        assert_eq!(10, tree.size(FsTree::ROOT));
+        assert_eq!(
+            vec![
+                Warning {
+                    line: 2,
+                    kind: WarningKind::OutputWithoutLs,
+                },
+                Warning {
+                    line: 4,
+                    kind: WarningKind::KindChanged("f".to_owned()),
+                },
+                Warning {
+                    line: 5,
+                    kind: WarningKind::SizeChanged {
+                        name: "f".to_owned(),
+                        old: 5,
+                        new: 7,
+                    },
+                },
+                Warning {
+                    line: 6,
+                    kind: WarningKind::CdIntoFile("f".to_owned()),
+                },
+                Warning {
+                    line: 7,
+                    kind: WarningKind::Unparseable("".to_owned()),
+                },
+                Warning {
+                    line: 8,
+                    kind: WarningKind::Unparseable("$ pwd".to_owned()),
+                },
+                Warning {
+                    line: 9,
+                    kind: WarningKind::OutputWithoutLs,
+                },
+            ],
+            warnings
+        );
+        // Skipped lines don't stop the rest of the transcript from being read:
+        assert_eq!(3, tree.size(tree.lookup("/g").unwrap()));
+    }
+
+    fn given_input() -> Vec<&'static str> {
+        vec![
+            "$ cd /",
+            "$ ls",
+            "dir a",
+            "14848514 b.txt",
+            "8504156 c.dat",
+            "dir d",
+            "$ cd a",
+            "$ ls",
+            "dir e",
+            "29116 f",
+            "2557 g",
+            "62596 h.lst",
+            "$ cd e",
+            "$ ls",
+            "584 i",
+            "$ cd ..",
+            "$ cd ..",
+            "$ cd d",
+            "$ ls",
+            "4060174 j",
+            "8033020 d.log",
+            "5626152 d.ext",
+            "7214296 k",
+        ]
+    }
+
+    #[test]
+    fn test_tree_view() {
+        let tree = read_transcript(parse(given_input()));
+        let expected = "\
+- / (dir, size=48381165)
+  - a (dir, size=94853)
+    - e (dir, size=584)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir, size=24933642)
+    - d.ext (file, size=5626152)
+    - d.log (file, size=8033020)
+    - j (file, size=4060174)
+    - k (file, size=7214296)
+";
+        assert_eq!(expected, view::tree(&tree, None));
+
+        let expected = "\
+- / (dir, size=48381165)
+  - a (dir, size=94853)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir, size=24933642)
+";
+        assert_eq!(expected, view::tree(&tree, Some(1)));
+    }
+
+    #[test]
+    fn test_du_view() {
+        let tree = read_transcript(parse(given_input()));
+        assert_eq!(
+            "   47M\t/\n   24M\t/d\n   93K\t/a\n   584\t/a/e\n",
+            view::du(&tree, None)
+        );
+        assert_eq!("   47M\t/\n", view::du(&tree, Some(0)));
+    }
+
+    #[test]
+    fn test_human_size() {
+        use view::human_size;
+        assert_eq!("0", human_size(0));
+        assert_eq!("1023", human_size(1023));
+        assert_eq!("1.0K", human_size(1024));
+        assert_eq!("1.1K", human_size(1025));
+        assert_eq!("10K", human_size(10 * 1024));
+        assert_eq!("11K", human_size(10 * 1024 + 1));
+        assert_eq!("1.0M", human_size(1024 * 1024));
+        assert_eq!("3.0G", human_size(3 << 30));
+    }
+
+    #[test]
+    fn test_cleanup_given_input() {
+        let tree = read_transcript(parse(given_input()));
+        let needed = 30000000 - (70000000 - 48381165);
+        let plan = cleanup::plan_cleanup(&tree, needed).unwrap();
+        assert_eq!(vec![tree.lookup("/d").unwrap()], plan.directories);
+        assert_eq!(24933642, plan.reclaimed);
+        assert_eq!(cleanup::Method::Exact, plan.method);
+    }
+
+    #[test]
+    fn test_cleanup_several_directories() {
+        let lines = vec![
+            "$ cd /",
+            "$ ls",
+            "dir big",
+            "dir p",
+            "dir q",
+            "$ cd big",
+            "$ ls",
+            "1000 f",
+            "dir inner",
+            "$ cd inner",
+            "$ ls",
+            "60 g",
+            "$ cd /",
+            "$ cd p",
+            "$ ls",
+            "70 f",
+            "$ cd /",
+            "$ cd q",
+            "$ ls",
+            "dir r",
+            "40 f",
+            "$ cd r",
+            "$ ls",
+            "50 f",
+        ];
+        let tree = read_transcript(parse(lines));
+
+        // Two small directories beat the one big one, and /q/r is enough without the rest of /q.
+        let plan = cleanup::plan_cleanup(&tree, 115).unwrap();
+        let paths: Vec<_> = plan.directories.iter().map(|&d| tree.path(d)).collect();
+        assert_eq!(vec!["/p", "/q/r"], paths);
+        assert_eq!(120, plan.reclaimed);
+
+        let plan = cleanup::plan_cleanup(&tree, 90).unwrap();
+        assert_eq!(vec![tree.lookup("/q").unwrap()], plan.directories);
+        assert_eq!(90, plan.reclaimed);
+
+        assert_eq!(None, cleanup::plan_cleanup(&tree, 100_000));
+    }
+
+    #[test]
+    fn test_cleanup_too_many_combinations() {
+        // Every subset of these directories reclaims a different amount.
+        let mut tree = FsTree::new();
+        for i in 0..12 {
+            let dir = tree.add_directory(FsTree::ROOT, &format!("d{i}"));
+            tree.add_file(dir, "f", 1 << i);
+        }
+
+        let plan = cleanup::plan_cleanup(&tree, 3000).unwrap();
+        let cleanup::Method::Approximate { within } = plan.method else {
+            panic!("4096 sums are too many to keep: {plan:?}");
+        };
+        // Exactly 3000 is possible:
+        assert!((3000..=3000 + within).contains(&plan.reclaimed));
+        let total: usize = plan.directories.iter().map(|&dir| tree.size(dir)).sum();
+        assert_eq!(plan.reclaimed, total);
+    }
+
+    /// About two hundred directories, nested a few deep, with files of all sorts of sizes, like a
+    /// real puzzle input.
+    fn realistic_tree() -> FsTree {
+        let mut tree = FsTree::new();
+        let mut dirs = vec![FsTree::ROOT];
+        for i in 0..180 {
+            let parent = dirs[i * 37 % dirs.len()];
+            let dir = tree.add_directory(parent, &format!("d{i}"));
+            for j in 0..i * 5 % 7 {
+                tree.add_file(
+                    dir,
+                    &format!("f{j}"),
+                    (i * 7919 + j * 104729) * 31 % 300_000,
+                );
+            }
+            dirs.push(dir);
+        }
+        tree.add_file(FsTree::ROOT, "big", 5_000_000);
+        tree
+    }
+
+    #[test]
+    fn test_cleanup_realistic_tree() {
+        let tree = realistic_tree();
+        let sizes = tree.sizes();
+        let subdirectories: usize = tree
+            .directories()
+            .filter(|&dir| tree.node(dir).parent == Some(FsTree::ROOT))
+            .map(|dir| sizes[dir])
+            .sum();
+
+        for needed in [10_000, 1_000_000, subdirectories / 2, subdirectories - 1000] {
+            let plan = cleanup::plan_cleanup(&tree, needed).unwrap();
+
+            assert!(!plan.directories.contains(&FsTree::ROOT));
+            for &a in &plan.directories {
+                for &b in &plan.directories {
+                    assert!(a == b || !tree.path(a).starts_with(&format!("{}/", tree.path(b))));
+                }
+            }
+            let total: usize = plan.directories.iter().map(|&dir| sizes[dir]).sum();
+            assert_eq!(plan.reclaimed, total);
+            assert!(plan.reclaimed >= needed);
+
+            // Never worse than deleting the one smallest directory that's big enough:
+            let part_2 = tree
+                .directories()
+                .filter(|&dir| dir != FsTree::ROOT && sizes[dir] >= needed)
+                .map(|dir| sizes[dir])
+                .min();
+            assert!(part_2.is_none_or(|size| plan.reclaimed <= size));
+        }
+
+        // Only deleting / itself would be enough:
+        assert_eq!(None, cleanup::plan_cleanup(&tree, subdirectories + 1));
+    }
+
+    #[test]
+    fn test_json() {
+        let tree = read_transcript(parse(vec![
+            "$ ls",
+            "dir a\"b",
+            "1 x",
+            "$ cd a\"b",
+            "$ ls",
+            "2 y",
+            "dir empty",
+        ]));
+        assert_eq!(
+            concat!(
+                r#"{"name": "/", "kind": "dir", "size": 3, "children": ["#,
+                r#"{"name": "a\"b", "kind": "dir", "size": 2, "children": ["#,
+                r#"{"name": "empty", "kind": "dir", "size": 0, "children": []}, "#,
+                r#"{"name": "y", "kind": "file", "size": 2}]}, "#,
+                r#"{"name": "x", "kind": "file", "size": 1}]}"#,
+            ),
+            export::to_json(&tree)
+        );
+    }
+
+    #[test]
+    fn test_materialize_round_trip() {
+        let parent = env::temp_dir().join(format!("day-7-materialize-{}", std::process::id()));
+        let tree = read_transcript(parse(given_input()));
+        let destination = export::materialize(&tree, &parent).unwrap();
+        let from_disk = export::read_from_disk(&destination);
+
+        // Running it again leaves the first copy alone:
+        let again = export::materialize(&tree, &parent);
+        let listing = std::fs::read_dir(&parent).map(|entries| entries.count());
+        std::fs::remove_dir_all(&parent).unwrap();
+        let from_disk = from_disk.unwrap();
+        assert_ne!(destination, again.unwrap());
+        assert_eq!(2, listing.unwrap());
+
+        let Calculation(size, dirs) = calculate_size(parse(given_input()), vec![]);
+        assert_eq!(size, from_disk.size(FsTree::ROOT));
+        for DirectorySize(name, size) in dirs {
+            let matching = from_disk
+                .directories()
+                .filter(|&dir| from_disk.node(dir).name == name)
+                .map(|dir| from_disk.size(dir))
+                .collect::<Vec<_>>();
+            assert_eq!(vec![size], matching, "{name}");
+        }
+        assert_eq!(
+            view::tree(&tree, None),
+            view::tree(&from_disk, None),
+            "same names, kinds, and sizes"
+        );
+    }
+
+    #[test]
+    fn test_materialize_refuses_to_escape() {
+        let destination = env::temp_dir().join(format!("day-7-escape-{}", std::process::id()));
+        let tree = read_transcript(parse(vec!["$ ls", "dir ..", "$ cd ..", "$ ls", "1 oops"]));
+        let result = export::materialize(&tree, &destination);
+        std::fs::remove_dir_all(&destination).unwrap();
+        assert_eq!(io::ErrorKind::InvalidInput, result.unwrap_err().kind());
+    }
+
+    #[test]
+    fn test_deeply_nested_transcript() {
+        const DEPTH: usize = 100_000;
+
+        // Every directory has a one-byte file and a subdirectory, 100k levels deep.
+        let mut lines = VecDeque::from([Line::Cd("/".to_owned())]);
+        for _ in 0..DEPTH {
+            lines.push_back(Line::Ls);
+            lines.push_back(Line::Directory("d".to_owned()));
+            lines.push_back(Line::File(1, "f".to_owned()));
+            lines.push_back(Line::Cd("d".to_owned()));
+        }
+        lines.push_back(Line::Ls);
+        lines.push_back(Line::File(1, "f".to_owned()));
+        for _ in 0..DEPTH {
+            lines.push_back(Line::Cd("..".to_owned()));
+        }
+
+        let Calculation(size, dirs) = calculate_size(lines, vec![]);
+        assert_eq!(DEPTH + 1, size);
+        assert_eq!(DEPTH, dirs.len());
+
+        let mut sizes: Vec<_> = dirs.iter().map(|DirectorySize(_, size)| *size).collect();
+        sizes.sort();
+        assert_eq!((1..=DEPTH).collect::<Vec<_>>(), sizes);
+    }
+
+    #[test]
+    fn test_deep_tree_views() {
+        const DEPTH: usize = 100_000;
+
+        let mut tree = FsTree::new();
+        let mut dir = FsTree::ROOT;
+        for _ in 0..DEPTH {
+            dir = tree.add_directory(dir, "d");
+        }
+        tree.add_file(dir, "f", 1);
+
+        assert_eq!(1, tree.size(FsTree::ROOT));
+        assert!(export::to_json(&tree).ends_with(&"]}".repeat(DEPTH + 1)));
+
+        let plan = cleanup::plan_cleanup(&tree, 1).unwrap();
+        assert_eq!(vec![dir], plan.directories);
+    }
 }
@@ -0,0 +1,194 @@
+use std::fmt::{self, Display};
+
+use crate::fs::{FsTree, NodeId, NodeKind};
+use crate::Line;
+
+/// Something odd in the transcript. The interpreter always carries on after a warning.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    /// One-indexed line of the transcript.
+    pub line: usize,
+    pub kind: WarningKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WarningKind {
+    /// `cd ..` while already in `/`; stayed in `/`.
+    CdAboveRoot,
+    /// `cd` into a directory that no `ls` has shown; it was created.
+    CdIntoUnlisted(String),
+    /// `cd` into something that is a file; stayed put.
+    CdIntoFile(String),
+    /// A `dir` or file line that wasn't preceded by `$ ls`; it was recorded anyway.
+    OutputWithoutLs,
+    /// The same name was listed both as a file and as a directory; the first one wins.
+    KindChanged(String),
+    /// A file was listed again with another size; the latest size wins.
+    SizeChanged {
+        name: String,
+        old: usize,
+        new: usize,
+    },
+    /// A line that isn't `cd`, `ls`, or their output; it was skipped.
+    Unparseable(String),
+}
+
+/// The result of replaying a transcript.
+#[derive(Debug)]
+pub struct Interpretation {
+    pub tree: FsTree,
+    pub warnings: Vec<Warning>,
+}
+
+/// Replays `cd` and `ls` commands with a real working directory stack.
+///
+/// Unlike a strict parser, it accepts `cd /` anywhere, the same directory being listed several
+/// times (entries are only counted once), and `cd` into directories that were never listed.
+#[derive(Debug)]
+pub struct Interpreter {
+    tree: FsTree,
+    /// Directories from the root down to the current working directory.
+    cwd: Vec<NodeId>,
+    /// Whether we're reading the output of `ls`.
+    listing: bool,
+    line_number: usize,
+    warnings: Vec<Warning>,
+}
+
+pub fn interpret(lines: impl IntoIterator<Item = Line>) -> Interpretation {
+    let mut interpreter = Interpreter::new();
+    for line in lines {
+        interpreter.feed(line);
+    }
+    interpreter.finish()
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Interpreter {
+            tree: FsTree::new(),
+            cwd: vec![FsTree::ROOT],
+            listing: false,
+            line_number: 0,
+            warnings: Vec::new(),
+        }
+    }
+
+    pub fn feed(&mut self, line: Line) {
+        self.line_number += 1;
+
+        match line {
+            Line::Cd(dir) => {
+                self.listing = false;
+                self.cd(&dir);
+            }
+            Line::Ls => self.listing = true,
+            Line::Directory(name) => {
+                self.check_listing();
+                let cwd = self.cwd();
+                match self.tree.child(cwd, &name) {
+                    Some(existing) if !self.tree.is_directory(existing) => {
+                        self.warn(WarningKind::KindChanged(name));
+                    }
+                    _ => {
+                        self.tree.add_directory(cwd, &name);
+                    }
+                }
+            }
+            Line::File(size, name) => {
+                self.check_listing();
+                let cwd = self.cwd();
+                match self.tree.child(cwd, &name) {
+                    None => {
+                        self.tree.add_file(cwd, &name, size);
+                    }
+                    Some(existing) => match self.tree.node(existing).kind {
+                        NodeKind::Directory(_) => self.warn(WarningKind::KindChanged(name)),
+                        NodeKind::File(old) if old != size => {
+                            self.tree.set_file_size(existing, size);
+                            self.warn(WarningKind::SizeChanged {
+                                name,
+                                old,
+                                new: size,
+                            });
+                        }
+                        NodeKind::File(_) => (),
+                    },
+                }
+            }
+            Line::Unparseable(text) => self.warn(WarningKind::Unparseable(text)),
+        }
+    }
+
+    pub fn finish(self) -> Interpretation {
+        Interpretation {
+            tree: self.tree,
+            warnings: self.warnings,
+        }
+    }
+
+    fn cwd(&self) -> NodeId {
+        *self.cwd.last().expect("the root is always on the stack")
+    }
+
+    fn cd(&mut self, dir: &str) {
+        match dir {
+            "/" => self.cwd.truncate(1),
+            ".." => {
+                if self.cwd.len() > 1 {
+                    self.cwd.pop();
+                } else {
+                    self.warn(WarningKind::CdAboveRoot);
+                }
+            }
+            name => match self.tree.child(self.cwd(), name) {
+                Some(child) if self.tree.is_directory(child) => self.cwd.push(child),
+                Some(_) => self.warn(WarningKind::CdIntoFile(name.to_owned())),
+                None => {
+                    let child = self.tree.add_directory(self.cwd(), name);
+                    self.cwd.push(child);
+                    self.warn(WarningKind::CdIntoUnlisted(name.to_owned()));
+                }
+            },
+        }
+    }
+
+    fn check_listing(&mut self) {
+        if !self.listing {
+            self.warn(WarningKind::OutputWithoutLs);
+            // Only warn once per run of output.
+            self.listing = true;
+        }
+    }
+
+    fn warn(&mut self, kind: WarningKind) {
+        self.warnings.push(Warning {
+            line: self.line_number,
+            kind,
+        });
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use WarningKind::*;
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            CdAboveRoot => write!(f, "cd .. from /; staying in /"),
+            CdIntoUnlisted(name) => write!(f, "cd into {name}, which was never listed"),
+            CdIntoFile(name) => write!(f, "cannot cd into file {name}"),
+            OutputWithoutLs => write!(f, "directory listing without ls"),
+            KindChanged(name) => write!(f, "{name} was listed as both a file and a directory"),
+            SizeChanged { name, old, new } => {
+                write!(f, "{name} changed size from {old} to {new}")
+            }
+            Unparseable(text) => write!(f, "skipping {text:?}, which is not cd, ls, or a listing"),
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use std::fmt::Write;
+
+use crate::fs::{FsTree, NodeKind};
+
+/// Renders the tree like the listing in the puzzle description, with sizes for directories too:
+///
+/// ```text
+/// - / (dir, size=48381165)
+///   - a (dir, size=94853)
+///     - e (dir, size=584)
+///       - i (file, size=584)
+/// ```
+///
+/// Entries deeper than `max_depth` (the root is at depth 0) are left out.
+pub fn tree(fs: &FsTree, max_depth: Option<usize>) -> String {
+    let sizes = fs.sizes();
+    let mut output = String::new();
+
+    for (id, depth) in fs.walk(max_depth) {
+        let node = fs.node(id);
+        let kind = match node.kind {
+            NodeKind::Directory(_) => "dir",
+            NodeKind::File(_) => "file",
+        };
+        let indent = "  ".repeat(depth);
+        writeln!(
+            output,
+            "{indent}- {} ({kind}, size={})",
+            node.name, sizes[id]
+        )
+        .unwrap();
+    }
+
+    output
+}
+
+/// Lists directories like `du -h`, biggest first.
+pub fn du(fs: &FsTree, max_depth: Option<usize>) -> String {
+    let sizes = fs.sizes();
+    let mut directories: Vec<_> = fs
+        .walk(max_depth)
+        .filter(|&(id, _)| fs.is_directory(id))
+        .map(|(id, _)| (sizes[id], fs.path(id)))
+        .collect();
+    directories.sort_by(|(a_size, a_path), (b_size, b_path)| {
+        b_size.cmp(a_size).then_with(|| a_path.cmp(b_path))
+    });
+
+    let mut output = String::new();
+    for (size, path) in directories {
+        writeln!(output, "{:>6}\t{path}", human_size(size)).unwrap();
+    }
+    output
+}
+
+/// Formats a size in bytes the way `du -h` does: powers of 1024, rounded up, with one decimal
+/// place for small numbers.
+pub fn human_size(bytes: usize) -> String {
+    const UNITS: [&str; 6] = ["K", "M", "G", "T", "P", "E"];
+
+    if bytes < 1024 {
+        return bytes.to_string();
+    }
+
+    let mut value = bytes as f64;
+    let mut unit = "";
+    for next_unit in UNITS {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next_unit;
+    }
+
+    let tenths = (value * 10.0).ceil() / 10.0;
+    if tenths < 10.0 {
+        format!("{tenths:.1}{unit}")
+    } else {
+        format!("{:.0}{unit}", value.ceil())
+    }
+}
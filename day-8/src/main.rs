@@ -1,68 +1,369 @@
-use std::io::{self, BufRead};
-use std::iter::repeat;
+use std::env;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
 use std::ops::Index;
+use std::path::{Path, PathBuf};
+use std::process;
+
+mod render;
 
 type Coords = (usize, usize);
 
+/// A step across the grid, `(dx, dy)`, with y growing downwards.
+type Direction = (isize, isize);
+
+/// Up, down, left and right, like in the puzzle.
+const CARDINAL: [Direction; 4] = [(0, -1), (0, 1), (-1, 0), (1, 0)];
+
+/// The cardinal directions, plus the diagonals.
+const EIGHT_WAYS: [Direction; 8] = [
+    (0, -1),
+    (0, 1),
+    (-1, 0),
+    (1, 0),
+    (-1, -1),
+    (1, -1),
+    (-1, 1),
+    (1, 1),
+];
+
+/// A rectangular forest of tree heights, at least one tree wide and one tree tall.
 #[derive(Debug)]
 struct Grid(Vec<Vec<u32>>);
 
-macro_rules! score {
-    ($dir: tt, $grid: ident, $x: ident, $y: ident) => {{
-        let mut score = 0;
-        for (x2, y2) in $grid.$dir(($x, $y)) {
-            score += 1;
-            if $grid[($x, $y)] <= $grid[(x2, y2)] {
-                break;
-            }
-        }
-        score
-    }};
+/// Why a forest couldn't be read. Rows and columns are one-indexed, like in a text editor.
+#[derive(Debug)]
+enum GridError {
+    Io(io::Error),
+    Empty,
+    NotADigit {
+        row: usize,
+        column: usize,
+        found: char,
+    },
+    Ragged {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
 }
 
+#[derive(Debug, Default)]
+struct Options {
+    render: bool,
+    ppm: Option<PathBuf>,
+    pgm: Option<PathBuf>,
+    input: Option<PathBuf>,
+    directions: Vec<Direction>,
+}
+
+/// Usage: `day-8 [--render] [--ppm FILE] [--pgm FILE] [--directions 4|8|CUSTOM] [PATH]`
+///
+/// `--render` shows the forest in colour, and `--ppm`/`--pgm` save a heatmap of scenic scores.
+/// The forest is read from `PATH`, or from stdin if there isn't one.
+///
+/// Trees look up, down, left and right, unless `--directions` says otherwise: `8` adds the
+/// diagonals, and anything else is a list of steps like `"1,2 -1,-2"` (see [`parse_directions`]).
 fn main() {
-    let trees = read_grid();
+    let options = parse_args(env::args().skip(1));
+    let trees = match read_grid(options.input.as_deref()) {
+        Ok(trees) => trees,
+        Err(error) => {
+            let source = options.input.as_deref().unwrap_or(Path::new("<stdin>"));
+            eprintln!("{}: {error}", source.display());
+            process::exit(1);
+        }
+    };
 
-    let width = trees.width();
-    let height = trees.height();
-
-    let best_score = (1..height - 1)
-        .flat_map(|y| {
-            // borrow `trees` here so that the closure doesn't try to move it...
-            // otherwise, we'd have to borrow `y`, and that's just silly.
-            let trees = &trees;
-            (1..width - 1).map(move |x| {
-                let mut scenic_score = 1;
-                scenic_score *= score!(above, trees, x, y);
-                scenic_score *= score!(below, trees, x, y);
-                scenic_score *= score!(left, trees, x, y);
-                scenic_score *= score!(right, trees, x, y);
-                scenic_score
-            })
-        })
-        .max()
-        .unwrap();
+    let directions = &options.directions;
+
+    let visible = count_visible(&trees, directions);
+    println!("{visible}");
 
+    let best_score = best_scenic_score(&trees, directions);
     println!("{best_score}");
+
+    if options.render || options.ppm.is_some() || options.pgm.is_some() {
+        let visible = visibility(&trees, directions);
+        let scores = scenic_scores(&trees, directions);
+
+        if options.render {
+            print!("{}", render::ansi(&trees, &visible, &scores));
+        }
+        if let Some(path) = options.ppm {
+            save(&path, render::ppm(&scores));
+        }
+        if let Some(path) = options.pgm {
+            save(&path, render::pgm(&scores));
+        }
+    }
+}
+
+/// Writes a heatmap, or exits with the reason it couldn't.
+fn save(path: &Path, contents: String) {
+    if let Err(error) = fs::write(path, contents) {
+        eprintln!("{}: {error}", path.display());
+        process::exit(1);
+    }
 }
 
-fn read_grid() -> Grid {
-    let stdin = io::stdin();
+fn parse_args(mut args: impl Iterator<Item = String>) -> Options {
+    let mut options = Options {
+        directions: CARDINAL.to_vec(),
+        ..Options::default()
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--render" => options.render = true,
+            "--directions" => {
+                let directions = args.next().expect("--directions needs 4, 8, or a list");
+                options.directions = parse_directions(&directions);
+            }
+            "--ppm" => options.ppm = Some(args.next().expect("--ppm needs a file").into()),
+            "--pgm" => options.pgm = Some(args.next().expect("--pgm needs a file").into()),
+            _ if arg.starts_with("--") => panic!("unknown argument {arg}"),
+            _ if options.input.is_none() => options.input = Some(arg.into()),
+            _ => panic!("only one forest at a time, please"),
+        }
+    }
+
+    options
+}
 
-    Grid(
-        stdin
-            .lock()
-            .lines()
-            .map(|line| {
-                let line = line.expect("line");
-                let line = line.trim();
-                line.chars().map(|c| c.to_digit(10).unwrap()).collect()
+/// `4` or `8`, or whitespace-separated `dx,dy` steps.
+///
+/// A step can be longer than one tree, like a knight's move: trees in between are skipped.
+fn parse_directions(spec: &str) -> Vec<Direction> {
+    match spec {
+        "4" => CARDINAL.to_vec(),
+        "8" => EIGHT_WAYS.to_vec(),
+        _ if spec.trim().is_empty() => panic!("trees have to look in at least one direction"),
+        _ => spec
+            .split_whitespace()
+            .map(|step| {
+                let (dx, dy) = step
+                    .split_once(',')
+                    .unwrap_or_else(|| panic!("expected dx,dy but got {step:?}"));
+                let direction = (
+                    dx.trim().parse().expect("dx should be an integer"),
+                    dy.trim().parse().expect("dy should be an integer"),
+                );
+                assert_ne!((0, 0), direction, "trees have to look somewhere");
+                direction
             })
             .collect(),
-    )
+    }
+}
+
+fn best_scenic_score(trees: &Grid, directions: &[Direction]) -> u64 {
+    scenic_scores(trees, directions)
+        .into_iter()
+        .flatten()
+        .max()
+        .unwrap()
+}
+
+/// The scenic score of every tree looking in each of `directions`, including the ones on the
+/// edge (which usually score 0).
+///
+/// Instead of walking outward from every tree, each line of sight is swept once, keeping a
+/// [`Horizon`] of the trees that could block the view, so this is O(width × height) per
+/// direction.
+fn scenic_scores(trees: &Grid, directions: &[Direction]) -> Vec<Vec<u64>> {
+    let mut scores = vec![vec![1; trees.width()]; trees.height()];
+
+    for &direction in directions {
+        sweep(trees, direction, Horizon::new, |horizon, (x, y), tree| {
+            scores[y][x] *= horizon.look(tree);
+        });
+    }
+
+    scores
+}
+
+/// Visits every tree after the next tree along its `direction`, passing along a bit of state
+/// for each line of sight. A tree whose ray leaves the grid straight away starts a new line with
+/// `start()`.
+///
+/// The grid is read a row at a time, however the lines of sight run across it.
+fn sweep<S>(
+    trees: &Grid,
+    direction: Direction,
+    mut start: impl FnMut() -> S,
+    mut visit: impl FnMut(&mut S, Coords, u32),
+) {
+    let width = trees.width();
+    let (dx, dy) = direction;
+
+    // The next tree along the ray has to be visited first.
+    let rows: Vec<usize> = if dy > 0 {
+        (0..trees.height()).rev().collect()
+    } else {
+        (0..trees.height()).collect()
+    };
+    let columns: Vec<usize> = if dy == 0 && dx > 0 {
+        (0..width).rev().collect()
+    } else {
+        (0..width).collect()
+    };
+
+    // Lines of sight, and which one each tree is on. The next tree along is at most |dy| rows
+    // away, so only the last few rows need remembering.
+    let mut lines: Vec<S> = Vec::new();
+    let window = dy.unsigned_abs() + 1;
+    let mut line_of = vec![0; window * width];
+    let slot = |y: isize| y.rem_euclid(window as isize) as usize * width;
+
+    for &y in &rows {
+        let (here, next_row) = (slot(y as isize), slot(y as isize + dy));
+        for &x in &columns {
+            let line = match trees.ray((x, y), direction).next() {
+                Some((next_x, _)) => line_of[next_row + next_x],
+                None => {
+                    lines.push(start());
+                    lines.len() - 1
+                }
+            };
+            visit(&mut lines[line], (x, y), trees[(x, y)]);
+            line_of[here + x] = line;
+        }
+    }
+}
+
+/// What a tree sees looking back along a line of sight towards the edge.
+///
+/// `blockers` is a monotonic stack of the trees that could still block the view of a later tree,
+/// tallest at the bottom, along with how far along the line they are. Shorter trees are popped as
+/// soon as a taller one hides them, so every tree is pushed and popped at most once. The edge
+/// itself is at the bottom, as a tree that's taller than anything.
+#[derive(Debug)]
+struct Horizon {
+    position: usize,
+    blockers: Vec<(usize, u32)>,
+}
+
+impl Horizon {
+    fn new() -> Self {
+        Horizon {
+            position: 0,
+            blockers: vec![(0, u32::MAX)],
+        }
+    }
+
+    /// How many trees the next tree along can see: up to and including the first tree at least
+    /// as tall, or up to the edge.
+    fn look(&mut self, height: u32) -> u64 {
+        let mut top = *self.blockers.last().unwrap();
+        while top.1 < height {
+            self.blockers.pop();
+            top = *self.blockers.last().unwrap();
+        }
+        let distance = self.position - top.0;
+        self.blockers.push((self.position, height));
+        self.position += 1;
+        distance as u64
+    }
+}
+
+/// Counts trees visible from outside the grid, looking back along any of `directions`.
+fn count_visible(trees: &Grid, directions: &[Direction]) -> usize {
+    visibility(trees, directions)
+        .iter()
+        .flatten()
+        .filter(|&&visible| visible)
+        .count()
+}
+
+/// Which trees are visible from outside the grid, looking back along any of `directions`.
+///
+/// Rather than looking outward from every tree, this sweeps each line of sight from the edge,
+/// keeping track of the tallest tree seen so far: a tree is visible from that edge if it's taller
+/// than everything before it. That's one pass over the grid per direction.
+fn visibility(trees: &Grid, directions: &[Direction]) -> Vec<Vec<bool>> {
+    let mut visible = vec![vec![false; trees.width()]; trees.height()];
+
+    for &direction in directions {
+        sweep(
+            trees,
+            direction,
+            || None,
+            |tallest: &mut Option<u32>, (x, y), tree| {
+                if tallest.is_none_or(|tallest| tree > tallest) {
+                    visible[y][x] = true;
+                    *tallest = Some(tree);
+                }
+            },
+        );
+    }
+
+    visible
+}
+
+fn read_grid(path: Option<&Path>) -> Result<Grid, GridError> {
+    match path {
+        Some(path) => Grid::open(path),
+        None => Grid::parse(io::stdin().lock()),
+    }
 }
 
 impl Grid {
+    fn open(path: &Path) -> Result<Grid, GridError> {
+        Grid::parse(BufReader::new(File::open(path)?))
+    }
+
+    /// Reads one row of digits per line. Blank lines at the end are ignored.
+    fn parse(input: impl BufRead) -> Result<Grid, GridError> {
+        let mut lines = input.lines().collect::<Result<Vec<_>, _>>()?;
+        while lines.last().is_some_and(|line| line.trim().is_empty()) {
+            lines.pop();
+        }
+
+        let mut rows: Vec<Vec<u32>> = Vec::with_capacity(lines.len());
+        for (i, line) in lines.iter().enumerate() {
+            let row = line
+                .trim_end()
+                .chars()
+                .enumerate()
+                .map(|(j, c)| {
+                    c.to_digit(10).ok_or(GridError::NotADigit {
+                        row: i + 1,
+                        column: j + 1,
+                        found: c,
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            if let Some(first) = rows.first() {
+                if row.len() != first.len() {
+                    return Err(GridError::Ragged {
+                        row: i + 1,
+                        expected: first.len(),
+                        found: row.len(),
+                    });
+                }
+            }
+            rows.push(row);
+        }
+
+        if rows.first().is_none_or(|row| row.is_empty()) {
+            return Err(GridError::Empty);
+        }
+        Ok(Grid(rows))
+    }
+
+    /// The trees from `from` (not included) out to the edge, taking `direction` steps.
+    fn ray(&self, (x, y): Coords, (dx, dy): Direction) -> impl Iterator<Item = Coords> + '_ {
+        debug_assert_ne!((0, 0), (dx, dy), "that ray never ends");
+        (1..).map_while(move |steps: isize| {
+            let x = x.checked_add_signed(dx * steps)?;
+            let y = y.checked_add_signed(dy * steps)?;
+            (x < self.width() && y < self.height()).then_some((x, y))
+        })
+    }
+
     fn width(&self) -> usize {
         self.0[0].len()
     }
@@ -70,21 +371,44 @@ impl Grid {
     fn height(&self) -> usize {
         self.0.len()
     }
+}
 
-    fn above(&self, (x, y): Coords) -> impl Iterator<Item = Coords> {
-        repeat(x).zip((0..y).rev())
-    }
-
-    fn below(&self, (x, y): Coords) -> impl Iterator<Item = Coords> {
-        repeat(x).zip(y + 1..self.height())
+impl Display for GridError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use GridError::*;
+        match self {
+            Io(error) => write!(f, "{error}"),
+            Empty => write!(f, "there are no trees"),
+            NotADigit { row, column, found } => {
+                write!(
+                    f,
+                    "row {row}, column {column}: {found:?} is not a tree height"
+                )
+            }
+            Ragged {
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "row {row}: expected {expected} trees like the first row, found {found}"
+            ),
+        }
     }
+}
 
-    fn left(&self, (x, y): Coords) -> impl Iterator<Item = Coords> {
-        (0..x).rev().zip(repeat(y))
+impl Error for GridError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GridError::Io(error) => Some(error),
+            _ => None,
+        }
     }
+}
 
-    fn right(&self, (x, y): Coords) -> impl Iterator<Item = Coords> {
-        (x + 1..self.width()).zip(repeat(y))
+impl From<io::Error> for GridError {
+    fn from(error: io::Error) -> Self {
+        GridError::Io(error)
     }
 }
 
@@ -94,3 +418,285 @@ impl Index<Coords> for Grid {
         &self.0[y][x]
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use proptest::prelude::*;
+    use proptest::strategy::ValueTree;
+    use proptest::test_runner::TestRunner;
+    use std::time::Instant;
+
+    /// The original implementation: walk outward from every tree.
+    fn naive_scenic_scores(trees: &Grid, directions: &[Direction]) -> Vec<Vec<u64>> {
+        (0..trees.height())
+            .map(|y| {
+                (0..trees.width())
+                    .map(|x| {
+                        let tree = trees[(x, y)];
+                        let mut scenic_score = 1;
+                        for &direction in directions {
+                            let mut score = 0;
+                            for other in trees.ray((x, y), direction) {
+                                score += 1;
+                                if tree <= trees[other] {
+                                    break;
+                                }
+                            }
+                            scenic_score *= score;
+                        }
+                        scenic_score
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn naive_visibility(trees: &Grid, directions: &[Direction]) -> Vec<Vec<bool>> {
+        (0..trees.height())
+            .map(|y| {
+                (0..trees.width())
+                    .map(|x| {
+                        let tree = trees[(x, y)];
+                        directions.iter().any(|&direction| {
+                            trees
+                                .ray((x, y), direction)
+                                .all(|other| trees[other] < tree)
+                        })
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// A row of random tree heights.
+    fn row(width: usize) -> impl Strategy<Value = Vec<u32>> {
+        proptest::collection::vec(0u32..=9, width)
+    }
+
+    /// Forests of random heights, from 1×1 up to `max_size` on each side.
+    fn forest(max_size: usize) -> impl Strategy<Value = Grid> {
+        (1..=max_size, 1..=max_size)
+            .prop_flat_map(|(width, height)| proptest::collection::vec(row(width), height))
+            .prop_map(Grid)
+    }
+
+    fn given_input() -> Grid {
+        Grid::parse(include_str!("../test-0.txt").as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_count_visible() {
+        assert_eq!(21, count_visible(&given_input(), &CARDINAL));
+
+        // Only the tree in the middle is hidden in every direction:
+        let grid = Grid::parse("11111\n19291\n12121\n19291\n11111".as_bytes()).unwrap();
+        assert_eq!(24, count_visible(&grid, &CARDINAL));
+    }
+
+    #[test]
+    fn test_best_scenic_score() {
+        assert_eq!(8, best_scenic_score(&given_input(), &CARDINAL));
+    }
+
+    #[test]
+    fn test_heatmaps() {
+        let trees = Grid::parse("303\n393\n303".as_bytes()).unwrap();
+        let scores = scenic_scores(&trees, &CARDINAL);
+        assert_eq!(vec![vec![0, 0, 0], vec![0, 1, 0], vec![0, 0, 0]], scores);
+
+        assert_eq!("P2\n3 3\n255\n0 0 0 0 255 0 0 0 0\n", render::pgm(&scores));
+
+        let ppm = render::ppm(&scores);
+        assert!(ppm.starts_with("P3\n3 3\n255\n68 1 84 68 1 84 "));
+        assert!(ppm.lines().all(|line| line.len() <= 70));
+        assert_eq!(4 + 3 * 9, ppm.split_whitespace().count());
+
+        let rendered = render::ansi(&trees, &visibility(&trees, &CARDINAL), &scores);
+        assert_eq!(3 + 1, rendered.lines().count(), "three rows and a legend");
+    }
+
+    #[test]
+    fn test_horizon() {
+        let look = |heights: &[u32]| {
+            let mut horizon = Horizon::new();
+            heights
+                .iter()
+                .map(|&height| horizon.look(height))
+                .collect::<Vec<_>>()
+        };
+        // The second 5 sees past the 3 and the 1, and stops at the first 5.
+        assert_eq!(vec![0, 1, 1, 3, 1], look(&[5, 3, 1, 5, 2]));
+        assert_eq!(vec![0, 1, 2, 3], look(&[1, 2, 3, 4]));
+        assert_eq!(vec![0, 1, 1, 1], look(&[4, 3, 2, 1]));
+    }
+
+    #[test]
+    fn test_scenic_scores_match_naive() {
+        let trees = given_input();
+        assert_eq!(
+            naive_scenic_scores(&trees, &CARDINAL),
+            scenic_scores(&trees, &CARDINAL)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_scenic_scores_match_naive(trees in forest(20)) {
+            prop_assert_eq!(
+                naive_scenic_scores(&trees, &CARDINAL),
+                scenic_scores(&trees, &CARDINAL)
+            );
+        }
+
+        #[test]
+        fn prop_directions_match_naive(
+            trees in forest(15),
+            extra in proptest::collection::vec((-3isize..=3, -3isize..=3), 0..=3),
+        ) {
+            let mut directions = EIGHT_WAYS.to_vec();
+            directions.extend(extra.into_iter().filter(|&step| step != (0, 0)));
+
+            prop_assert_eq!(
+                naive_scenic_scores(&trees, &directions),
+                scenic_scores(&trees, &directions),
+                "{:?}", directions
+            );
+            prop_assert_eq!(
+                naive_visibility(&trees, &directions),
+                visibility(&trees, &directions),
+                "{:?}", directions
+            );
+        }
+    }
+
+    /// `cargo test --release -- --ignored --nocapture bench` to compare with the naive version.
+    ///
+    /// Heights are single digits, so even the naive walk stops within about ten trees; it's at
+    /// its worst on long slopes, where every tree sees as far as it possibly can.
+    #[test]
+    #[ignore]
+    fn bench_scenic_scores() {
+        const SIZE: usize = 5000;
+        let mut runner = TestRunner::deterministic();
+        let random = Grid(
+            (0..SIZE)
+                .map(|_| row(SIZE).new_tree(&mut runner).unwrap().current())
+                .collect(),
+        );
+        let slopes = Grid(
+            (0..SIZE)
+                .map(|y| (0..SIZE).map(|x| ((x + 2 * y) % 10) as u32).collect())
+                .collect(),
+        );
+
+        for (name, trees) in [("random", random), ("slopes", slopes)] {
+            let start = Instant::now();
+            let fast = scenic_scores(&trees, &CARDINAL);
+            let fast_time = start.elapsed();
+
+            let start = Instant::now();
+            let naive = naive_scenic_scores(&trees, &CARDINAL);
+            let naive_time = start.elapsed();
+
+            println!("{name}: monotonic stacks {fast_time:?}, naive {naive_time:?}");
+            assert_eq!(naive, fast);
+        }
+    }
+
+    #[test]
+    fn test_grid_errors() {
+        let parse = |input: &str| Grid::parse(input.as_bytes());
+
+        assert!(matches!(
+            parse("123\n4x6\n789"),
+            Err(GridError::NotADigit {
+                row: 2,
+                column: 2,
+                found: 'x'
+            })
+        ));
+        assert!(matches!(
+            parse("123\n45\n789"),
+            Err(GridError::Ragged {
+                row: 2,
+                expected: 3,
+                found: 2
+            })
+        ));
+        assert!(matches!(
+            parse("123\n\n789"),
+            Err(GridError::Ragged { row: 2, .. })
+        ));
+        assert!(matches!(parse(""), Err(GridError::Empty)));
+        assert!(matches!(parse("\n\n"), Err(GridError::Empty)));
+
+        let error = parse("12\n3-").unwrap_err();
+        assert_eq!(
+            "row 2, column 2: '-' is not a tree height",
+            error.to_string()
+        );
+
+        // Windows line endings and a trailing blank line are fine:
+        let trees = parse("12\r\n34\r\n\r\n").unwrap();
+        assert_eq!((2, 2), (trees.width(), trees.height()));
+    }
+
+    #[test]
+    fn test_thin_forests() {
+        for input in ["3", "30373", "3\n0\n3\n7\n3"] {
+            let trees = Grid::parse(input.as_bytes()).unwrap();
+            let visible = input.len() - input.matches('\n').count();
+            assert_eq!(visible, count_visible(&trees, &CARDINAL));
+            assert_eq!(0, best_scenic_score(&trees, &CARDINAL));
+            assert_eq!(
+                naive_scenic_scores(&trees, &CARDINAL),
+                scenic_scores(&trees, &CARDINAL)
+            );
+        }
+    }
+
+    #[test]
+    fn test_open() {
+        let trees = Grid::open(Path::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/test-0.txt"
+        )));
+        assert_eq!(21, count_visible(&trees.unwrap(), &CARDINAL));
+
+        let missing = Grid::open(Path::new("no-such-forest.txt"));
+        assert!(matches!(missing, Err(GridError::Io(_))));
+    }
+
+    #[test]
+    fn test_ray() {
+        let trees = given_input();
+        let ray: Vec<_> = trees.ray((1, 1), (1, 1)).collect();
+        assert_eq!(vec![(2, 2), (3, 3), (4, 4)], ray);
+        let ray: Vec<_> = trees.ray((1, 3), (2, -1)).collect();
+        assert_eq!(vec![(3, 2)], ray);
+        assert_eq!(0, trees.ray((0, 2), (-1, 0)).count());
+        assert_eq!(4, trees.ray((0, 4), (0, -1)).count());
+    }
+
+    #[test]
+    fn test_directions() {
+        assert_eq!(CARDINAL.to_vec(), parse_directions("4"));
+        assert_eq!(EIGHT_WAYS.to_vec(), parse_directions("8"));
+        assert_eq!(vec![(1, 2), (-2, -1)], parse_directions(" 1,2  -2,-1 "));
+        assert!(std::panic::catch_unwind(|| parse_directions("")).is_err());
+        assert!(std::panic::catch_unwind(|| parse_directions(" \t ")).is_err());
+
+        // Looking in more directions can only make more trees visible...
+        let trees = given_input();
+        assert!(count_visible(&trees, &EIGHT_WAYS) >= count_visible(&trees, &CARDINAL));
+        // ...and on the edge, trees see nothing in some direction.
+        let scores = scenic_scores(&trees, &EIGHT_WAYS);
+        assert!(scores[0].iter().all(|&score| score == 0));
+
+        // A lone tall tree in the middle sees everything, in every direction:
+        let trees = Grid::parse("000\n090\n000".as_bytes()).unwrap();
+        assert_eq!(1, best_scenic_score(&trees, &EIGHT_WAYS));
+        assert_eq!(9, count_visible(&trees, &EIGHT_WAYS));
+    }
+}
@@ -0,0 +1,127 @@
+use std::fmt::Write;
+
+use crate::Grid;
+
+/// Viridis, roughly: dark purple for the worst scores, yellow for the best.
+const GRADIENT: [(u8, u8, u8); 5] = [
+    (68, 1, 84),
+    (59, 82, 139),
+    (33, 145, 140),
+    (94, 201, 98),
+    (253, 231, 37),
+];
+
+/// Shows each tree's height on a background coloured by its scenic score. Trees visible from
+/// outside are bold; the rest are dimmed. The best tree is underlined in red.
+pub fn ansi(trees: &Grid, visible: &[Vec<bool>], scores: &[Vec<u64>]) -> String {
+    let max = max_score(scores);
+    let best = scores
+        .iter()
+        .enumerate()
+        .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, &score)| (score, x, y)))
+        .max_by_key(|&(score, _, _)| score)
+        .map(|(_, x, y)| (x, y));
+
+    let mut output = String::new();
+    for (y, row) in scores.iter().enumerate() {
+        for (x, &score) in row.iter().enumerate() {
+            let (r, g, b) = colour(intensity(score, max));
+            let style = if best == Some((x, y)) {
+                "1;4;38;2;255;0;0"
+            } else if visible[y][x] {
+                "1;97"
+            } else {
+                "2;37"
+            };
+            write!(
+                output,
+                "\x1b[48;2;{r};{g};{b}m\x1b[{style}m{}",
+                trees[(x, y)]
+            )
+            .unwrap();
+            output.push_str("\x1b[0m");
+        }
+        output.push('\n');
+    }
+
+    write!(output, "scenic score: 0 ").unwrap();
+    for step in 0..=10 {
+        let (r, g, b) = colour(step as f64 / 10.0);
+        write!(output, "\x1b[48;2;{r};{g};{b}m \x1b[0m").unwrap();
+    }
+    writeln!(output, " {max} (log scale); bold = visible; red = best").unwrap();
+
+    output
+}
+
+/// A plain (ASCII) PPM image of the scenic scores, one pixel per tree.
+pub fn ppm(scores: &[Vec<u64>]) -> String {
+    let max = max_score(scores);
+    let samples = scores.iter().flatten().flat_map(|&score| {
+        let (r, g, b) = colour(intensity(score, max));
+        [r, g, b]
+    });
+    netpbm("P3", scores, samples)
+}
+
+/// A plain (ASCII) PGM image of the scenic scores, one pixel per tree.
+pub fn pgm(scores: &[Vec<u64>]) -> String {
+    let max = max_score(scores);
+    let samples = scores
+        .iter()
+        .flatten()
+        .map(|&score| (intensity(score, max) * 255.0).round() as u8);
+    netpbm("P2", scores, samples)
+}
+
+fn netpbm(magic: &str, scores: &[Vec<u64>], samples: impl Iterator<Item = u8>) -> String {
+    // Lines in plain netpbm files shouldn't be longer than 70 characters.
+    const MAX_LINE: usize = 70;
+
+    let width = scores.first().map_or(0, Vec::len);
+    let height = scores.len();
+    let mut output = format!("{magic}\n{width} {height}\n255\n");
+    let mut line = String::new();
+    for sample in samples {
+        let sample = sample.to_string();
+        if !line.is_empty() && line.len() + 1 + sample.len() > MAX_LINE {
+            output.push_str(&line);
+            output.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&sample);
+    }
+    if !line.is_empty() {
+        output.push_str(&line);
+        output.push('\n');
+    }
+    output
+}
+
+fn max_score(scores: &[Vec<u64>]) -> u64 {
+    scores.iter().flatten().copied().max().unwrap_or(0)
+}
+
+/// Where a score falls between 0 and the best score, from 0.0 to 1.0. Scores are products, so a
+/// few trees score far higher than the rest; a log scale keeps the others from all looking the
+/// same.
+fn intensity(score: u64, max: u64) -> f64 {
+    if max == 0 {
+        return 0.0;
+    }
+    (1.0 + score as f64).ln() / (1.0 + max as f64).ln()
+}
+
+fn colour(t: f64) -> (u8, u8, u8) {
+    let position = t.clamp(0.0, 1.0) * (GRADIENT.len() - 1) as f64;
+    let i = (position.floor() as usize).min(GRADIENT.len() - 2);
+    let fraction = position - i as f64;
+    let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * fraction).round() as u8;
+
+    let (r0, g0, b0) = GRADIENT[i];
+    let (r1, g1, b1) = GRADIENT[i + 1];
+    (mix(r0, r1), mix(g0, g1), mix(b0, b1))
+}
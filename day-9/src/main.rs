@@ -1,29 +1,77 @@
 use inpt::{self, Inpt};
 use std::cmp::Ordering;
-use std::collections::HashSet;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter};
+use std::iter;
+use std::path::PathBuf;
 
-const N_KNOTS: usize = 10;
+mod render;
+mod stats;
 
-type Coords = (i32, i32);
+use render::Playback;
+use stats::{KnotStats, TrailCsv};
+
+/// Knots in the rope for part 2; part 1 is `day-9 2`.
+const DEFAULT_KNOTS: usize = 10;
+
+/// x grows to the right and y grows downwards. There are no bounds: the rope can wander off
+/// as far as it likes.
+type Coords = (i64, i64);
+
+/// Where every knot starts.
+const ORIGIN: Coords = (0, 0);
+
+#[derive(Debug)]
+struct Options {
+    knots: usize,
+    all: bool,
+    /// Frames per second.
+    animate: Option<f64>,
+    frames: Option<PathBuf>,
+    stats: bool,
+    csv: Option<PathBuf>,
+}
 
 #[derive(Debug)]
 pub struct World {
-    width: i32,
-    height: i32,
     knots: Vec<Coords>,
-    #[cfg(debug_assertions)]
-    start: Coords,
+    /// How many times the head has moved.
+    steps: usize,
+}
+
+/// Something that watches the rope move.
+pub trait Observer {
+    /// Sees the rope where it starts, and then after every step.
+    fn observe(&mut self, world: &World);
+}
+
+impl<F: FnMut(&World)> Observer for F {
+    fn observe(&mut self, world: &World) {
+        self(world)
+    }
 }
 
-#[derive(Inpt, Debug, Clone, Copy)]
-#[inpt(regex = r"(\w+) (\d+)")]
-struct Movement {
-    direction: Direction,
-    steps: i32,
+#[derive(Inpt, Debug, Clone, Copy, PartialEq, Eq)]
+enum Movement {
+    /// `V 3 -2`: head for a point relative to where the head is, diagonally first.
+    #[inpt(regex = r"V (-?\d+) (-?\d+)")]
+    Vector(i64, i64),
+    /// `R 4`, `UL 2`, ...
+    #[inpt(regex = r"(\w+) (\d+)")]
+    Steps(Direction, u32),
 }
 
-#[derive(Inpt, Debug, Clone, Copy)]
+#[derive(Inpt, Debug, Clone, Copy, PartialEq, Eq)]
 enum Direction {
+    #[inpt(regex = r"UR")]
+    UpRight,
+    #[inpt(regex = r"UL")]
+    UpLeft,
+    #[inpt(regex = r"DR")]
+    DownRight,
+    #[inpt(regex = r"DL")]
+    DownLeft,
     #[inpt(regex = r"R")]
     Right,
     #[inpt(regex = r"U")]
@@ -34,74 +82,190 @@ enum Direction {
     Left,
 }
 
-#[inpt::main]
-fn main(head_movement: Vec<Movement>) {
-    let mut world = World::from_movements(&head_movement);
-    print::initial_state(&world);
+/// Usage: `day-9 [--all] [--stats] [--csv FILE] [--animate FPS] [--frames DIR] [KNOTS]`
+///
+/// Counts the positions visited by the tail of a rope with `KNOTS` knots (10 by default). With
+/// `--all`, reports the count for every rope from 2 knots up to `KNOTS`, all from a single run.
+///
+/// `--stats` shows how many positions each knot visited and how far it moved, and `--csv` saves
+/// the position of every knot after every step.
+///
+/// `--animate` plays the rope in the terminal, and `--frames` saves every step as a numbered PPM
+/// in `DIR` (`ffmpeg -i DIR/frame-%06d.ppm rope.gif` makes them into a GIF). Either way, the
+/// camera follows the head, and the tail's trail is drawn in.
+///
+/// Movements are read from stdin as they come, so the input can be as long as you like.
+fn main() {
+    let options = parse_args(env::args().skip(1));
+    let mut world = World::new(options.knots);
+
+    let stdin = io::stdin();
+    let head_movement = stdin
+        .lock()
+        .lines()
+        .map(|line| line.expect("line"))
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| inpt::inpt::<Movement>(line.trim()).unwrap());
+
+    let mut stats = KnotStats::new();
+    let mut observers: Vec<&mut dyn Observer> = vec![&mut stats];
+
+    let mut csv = options.csv.as_ref().map(|path| {
+        let file = File::create(path).expect("create the CSV file");
+        TrailCsv::new(BufWriter::new(file))
+    });
+    if let Some(csv) = &mut csv {
+        observers.push(csv);
+    }
 
-    let mut tail_coords: HashSet<_> = HashSet::new();
-    tail_coords.insert(world.tail());
+    let mut playback = (options.animate.is_some() || options.frames.is_some())
+        .then(|| Playback::new(options.animate, options.frames.clone()));
+    if let Some(playback) = &mut playback {
+        observers.push(playback);
+    }
 
-    for m in head_movement {
-        print::header(format!("{m:?}"));
+    world.run(head_movement, &mut observers);
 
-        for _ in 0..m.steps {
-            world.move_head_once(m.direction);
-            world.move_knots_once();
-            tail_coords.insert(world.tail());
-            print::step(&world);
+    if let Some(csv) = csv {
+        csv.finish().expect("write the CSV file");
+    }
+    if options.stats {
+        print!("{}", stats.report());
+    }
+    if options.all {
+        for (knot, visited) in stats.visited.iter().enumerate().skip(1) {
+            println!("{} knots: {}", knot + 1, visited.len());
         }
+    } else {
+        println!("Positions: {}", stats.visited.last().unwrap().len());
     }
-    print::trail(&tail_coords, &world);
-
-    println!("Positions: {}", tail_coords.len());
 }
 
-impl World {
-    fn from_movements(head_movement: &[Movement]) -> Self {
-        use Direction::*;
-
-        let mut min_width = 0;
-        let mut max_width = 1;
-        let mut min_height = 0;
-        let mut max_height = 1;
-        let mut x = 0;
-        let mut y = 0;
-
-        for m in head_movement {
-            let d = m.steps;
-            match m.direction {
-                Right => x += d,
-                Left => x -= d,
-                Up => y -= d,
-                Down => y += d,
+fn parse_args(mut args: impl Iterator<Item = String>) -> Options {
+    let mut options = Options {
+        knots: DEFAULT_KNOTS,
+        all: false,
+        animate: None,
+        frames: None,
+        stats: false,
+        csv: None,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--all" => options.all = true,
+            "--stats" => options.stats = true,
+            "--csv" => options.csv = Some(args.next().expect("--csv needs a file").into()),
+            "--animate" => {
+                let fps: f64 = args
+                    .next()
+                    .and_then(|fps| fps.parse().ok())
+                    .expect("--animate needs a frame rate");
+                assert!(fps > 0.0, "the frame rate has to be positive");
+                options.animate = Some(fps);
             }
-
-            if x >= max_width {
-                max_width = x + 1;
+            "--frames" => {
+                options.frames = Some(args.next().expect("--frames needs a directory").into())
             }
-            if x < min_width {
-                min_width = x;
+            _ => {
+                options.knots = arg
+                    .parse()
+                    .unwrap_or_else(|_| panic!("unknown argument {arg}"));
+                assert!(options.knots >= 1, "a rope needs at least one knot");
             }
-            if y >= max_height {
-                max_height = y + 1;
+        }
+    }
+
+    options
+}
+
+impl Movement {
+    /// The movement, one tree at a time.
+    fn directions(self) -> impl Iterator<Item = Direction> {
+        let (mut dx, mut dy) = match self {
+            Movement::Vector(dx, dy) => (dx, dy),
+            Movement::Steps(direction, steps) => {
+                let (dx, dy) = direction.delta();
+                (dx * steps as i64, dy * steps as i64)
             }
-            if y < min_height {
-                min_height = y;
+        };
+
+        iter::from_fn(move || {
+            if (dx, dy) == (0, 0) {
+                return None;
             }
+            let step = (dx.signum(), dy.signum());
+            dx -= step.0;
+            dy -= step.1;
+            Some(Direction::from_delta(step))
+        })
+    }
+}
+
+impl Direction {
+    fn delta(self) -> Coords {
+        use Direction::*;
+        match self {
+            Right => (1, 0),
+            Up => (0, -1),
+            Down => (0, 1),
+            Left => (-1, 0),
+            UpRight => (1, -1),
+            UpLeft => (-1, -1),
+            DownRight => (1, 1),
+            DownLeft => (-1, 1),
         }
+    }
 
-        let width = max_width - min_width;
-        let height = max_height - min_height;
-        let start = (0 - min_width, 0 - min_height);
-        let knots: Vec<_> = (0..N_KNOTS).map(|_| start).collect();
+    /// The direction of a step of at most one tree in each axis.
+    fn from_delta(delta: Coords) -> Self {
+        use Direction::*;
+        match delta {
+            (1, 0) => Right,
+            (0, -1) => Up,
+            (0, 1) => Down,
+            (-1, 0) => Left,
+            (1, -1) => UpRight,
+            (-1, -1) => UpLeft,
+            (1, 1) => DownRight,
+            (-1, 1) => DownLeft,
+            _ => panic!("{delta:?} is not a single step"),
+        }
+    }
+}
 
+impl World {
+    fn new(n_knots: usize) -> Self {
         World {
-            width,
-            height,
-            knots,
-            #[cfg(debug_assertions)]
-            start,
+            knots: vec![ORIGIN; n_knots],
+            steps: 0,
+        }
+    }
+
+    /// Moves the rope through all of `head_movement`, one step at a time, showing each step to
+    /// every observer.
+    ///
+    /// A knot only ever follows the knots in front of it, so knot `i` moves the same way no matter
+    /// how long the rope is: its trail is also the tail's trail for a rope of `i + 1` knots.
+    fn run(
+        &mut self,
+        head_movement: impl IntoIterator<Item = Movement>,
+        observers: &mut [&mut dyn Observer],
+    ) {
+        let mut notify = |world: &World| {
+            for observer in observers.iter_mut() {
+                observer.observe(world);
+            }
+        };
+
+        notify(self);
+        for m in head_movement {
+            for direction in m.directions() {
+                self.move_head_once(direction);
+                self.move_knots_once();
+                self.steps += 1;
+                notify(self);
+            }
         }
     }
 
@@ -110,7 +274,11 @@ impl World {
     }
 
     fn tail(&self) -> Coords {
-        self.knots[N_KNOTS - 1]
+        *self.knots.last().unwrap()
+    }
+
+    fn steps(&self) -> usize {
+        self.steps
     }
 
     fn set_head_position(&mut self, new_position: Coords) {
@@ -119,30 +287,19 @@ impl World {
 
     fn move_head_once(&mut self, dir: Direction) {
         let (x, y) = self.head();
-        use Direction::*;
-        let new_pos = match dir {
-            Right => (x + 1, y),
-            Up => (x, y - 1),
-            Down => (x, y + 1),
-            Left => (x - 1, y),
-        };
-        let (x, y) = new_pos;
-        assert!(x >= 0);
-        assert!(x < self.width);
-        assert!(y >= 0);
-        assert!(y < self.height);
-        self.set_head_position(new_pos);
+        let (dx, dy) = dir.delta();
+        self.set_head_position((x + dx, y + dy));
     }
 
     fn move_knots_once(&mut self) {
-        for knot in 1..N_KNOTS {
+        for knot in 1..self.knots.len() {
             self.move_knot_once(knot);
         }
     }
 
     fn move_knot_once(&mut self, which: usize) {
         assert!(which >= 1);
-        assert!(which < N_KNOTS);
+        assert!(which < self.knots.len());
         let (x, y) = self.knots[which];
         let (other_x, other_y) = self.knots[which - 1];
         let dx = x - other_x;
@@ -172,25 +329,6 @@ impl World {
         self.knots[which] = (x, y);
     }
 
-    #[cfg(debug_assertions)]
-    fn print(&self) {
-        for y in 0..self.height {
-            for x in 0..self.width {
-                if (x, y) == self.head() {
-                    print!("H");
-                } else if let Some(i) = self.find_knot((x, y)) {
-                    print!("{i}");
-                } else if (x, y) == self.start {
-                    print!("s");
-                } else {
-                    print!(".");
-                }
-            }
-            println!();
-        }
-    }
-
-    #[cfg(debug_assertions)]
     fn find_knot(&self, (x, y): Coords) -> Option<usize> {
         self.knots
             .iter()
@@ -198,56 +336,192 @@ impl World {
             .find(|&(_, knot)| (x, y) == *knot)
             .map(|(i, _)| i)
     }
+}
 
-    #[cfg(debug_assertions)]
-    fn print_trail(&self, trail: &HashSet<Coords>) {
-        for y in 0..self.height {
-            for x in 0..self.width {
-                if trail.contains(&(x, y)) {
-                    print!("#");
-                } else {
-                    print!(".");
-                }
-            }
-            println!();
-        }
+#[cfg(test)]
+mod test {
+    use super::*;
+    use render::Camera;
+    use std::collections::HashSet;
+
+    fn movements(text: &str) -> Vec<Movement> {
+        text.lines().map(|line| inpt::inpt(line).unwrap()).collect()
     }
-}
 
-// printing non-sense:
+    /// Runs the rope, and returns where each knot has been.
+    fn follow(
+        world: &mut World,
+        head_movement: impl IntoIterator<Item = Movement>,
+    ) -> Vec<HashSet<Coords>> {
+        let mut stats = KnotStats::new();
+        world.run(head_movement, &mut [&mut stats]);
+        stats.visited
+    }
 
-#[cfg(debug_assertions)]
-mod print {
-    use super::*;
+    fn tail_visits(head_movement: &[Movement], n_knots: usize) -> usize {
+        let mut world = World::new(n_knots);
+        follow(&mut world, head_movement.iter().copied())
+            .last()
+            .unwrap()
+            .len()
+    }
 
-    pub fn initial_state(world: &World) {
-        println!("== Initial State ==");
-        println!();
-        world.print();
-        println!();
+    const LARGER_EXAMPLE: &str = "R 5\nU 8\nL 8\nD 3\nR 17\nD 10\nL 25\nU 20";
+
+    #[test]
+    fn test_given_input() {
+        let head_movement = movements(include_str!("../test-0.txt"));
+        assert_eq!(13, tail_visits(&head_movement, 2));
+        assert_eq!(1, tail_visits(&head_movement, 10));
+
+        assert_eq!(36, tail_visits(&movements(LARGER_EXAMPLE), 10));
     }
 
-    pub fn header(text: String) {
-        println!("== {} ==", &text);
-        println!();
+    #[test]
+    fn test_every_rope_length_at_once() {
+        for text in [include_str!("../test-0.txt"), LARGER_EXAMPLE] {
+            let head_movement = movements(text);
+            let mut world = World::new(10);
+            let trails = follow(&mut world, head_movement.iter().copied());
+
+            for n_knots in 1..=10 {
+                assert_eq!(
+                    tail_visits(&head_movement, n_knots),
+                    trails[n_knots - 1].len(),
+                    "{n_knots} knots"
+                );
+            }
+        }
     }
 
-    pub fn step(world: &World) {
-        world.print();
-        println!();
+    #[test]
+    fn test_unbounded() {
+        // Heads up and to the left of the start, where coordinates are negative:
+        let head_movement = movements("L 300\nU 5");
+        let mut world = World::new(2);
+        let trails = follow(&mut world, head_movement);
+        assert_eq!((-300, -5), world.head());
+        assert_eq!(300 + 4, trails[1].len());
+        assert!(trails[1].contains(&(-300, -4)));
+
+        // Movements are taken as needed, so they can go on forever:
+        let endless = iter::repeat(Movement::Steps(Direction::Right, 1));
+        let mut world = World::new(10);
+        let trails = follow(&mut world, endless.take(1000));
+        assert_eq!((1000, 0), world.head());
+        assert_eq!(1000 - 8, trails[9].len());
     }
 
-    pub fn trail(coords: &HashSet<Coords>, world: &World) {
-        world.print_trail(coords);
+    #[test]
+    fn test_parse_movements() {
+        use Direction::*;
+        assert_eq!(
+            vec![
+                Movement::Steps(Right, 4),
+                Movement::Steps(UpRight, 2),
+                Movement::Steps(DownLeft, 1),
+                Movement::Vector(3, -2),
+                Movement::Vector(-1, 0),
+            ],
+            movements("R 4\nUR 2\nDL 1\nV 3 -2\nV -1 0")
+        );
+
+        let directions: Vec<_> = Movement::Vector(3, -2).directions().collect();
+        assert_eq!(vec![UpRight, UpRight, Right], directions);
+        assert_eq!(0, Movement::Vector(0, 0).directions().count());
     }
-}
 
-#[cfg(not(debug_assertions))]
-mod print {
-    use super::*;
+    #[test]
+    fn test_diagonal_moves() {
+        // The tail only has to move once the head is two trees away:
+        let mut world = World::new(2);
+        let trails = follow(&mut world, movements("UR 1"));
+        assert_eq!(vec![(1, -1), (0, 0)], world.knots);
+        assert_eq!(1, trails[1].len());
+
+        let mut world = World::new(3);
+        let trails = follow(&mut world, movements("UR 3\nD 2"));
+        assert_eq!(vec![(3, -1), (2, -2), (1, -1)], world.knots);
+        assert_eq!(HashSet::from([(0, 0), (1, -1)]), trails[2]);
+
+        // Two steps diagonally, then one to the right:
+        let mut world = World::new(2);
+        let trails = follow(&mut world, movements("V 3 -2"));
+        assert_eq!(vec![(3, -2), (2, -2)], world.knots);
+        assert_eq!(HashSet::from([(0, 0), (1, -1), (2, -2)]), trails[1]);
+
+        // Diagonal steps and the matching vector end up in the same place:
+        let mut world = World::new(10);
+        let by_steps = follow(&mut world, movements("DL 5\nUL 5\nR 7"));
+        let mut world = World::new(10);
+        let by_vectors = follow(&mut world, movements("V -5 5\nV -5 -5\nV 7 0"));
+        assert_eq!(by_steps, by_vectors);
+    }
+
+    #[test]
+    fn test_render_text() {
+        let mut world = World::new(2);
+        let trails = follow(&mut world, movements("R 4"));
+        let mut camera = Camera::new(6, 3);
+        camera.follow(world.head());
+        assert_eq!(
+            "......\ns##1H.\n......\n",
+            render::text(&world, &trails[1], &camera)
+        );
+
+        let ppm = render::ppm(&world, &trails[1], &camera);
+        let header = "P6\n48 24\n255\n";
+        assert!(ppm.starts_with(header.as_bytes()));
+        assert_eq!(header.len() + 48 * 24 * 3, ppm.len());
+    }
 
-    pub fn initial_state(_: &World) {}
-    pub fn header(_: String) {}
-    pub fn step(_: &World) {}
-    pub fn trail(_: &HashSet<Coords>, _: &World) {}
+    #[test]
+    fn test_camera_follows_head() {
+        let mut camera = Camera::new(render::VIEW_WIDTH, render::VIEW_HEIGHT);
+        let mut trail = HashSet::new();
+        let mut check_frame = |world: &World| {
+            camera.follow(world.head());
+            trail.insert(world.tail());
+            let frame = render::text(world, &trail, &camera);
+            assert_eq!(1, frame.matches('H').count(), "{frame}");
+        };
+        let mut world = World::new(10);
+        world.run(
+            movements("R 100\nU 50\nV -70 80\nDL 30"),
+            &mut [&mut check_frame],
+        );
+    }
+
+    #[test]
+    fn test_knot_stats() {
+        let mut stats = KnotStats::new();
+        let mut csv = TrailCsv::new(Vec::new());
+        let mut world = World::new(3);
+        world.run(movements("R 2\nUR 2"), &mut [&mut stats, &mut csv]);
+
+        // The head ends up at (4, -2), and drags the others along behind it:
+        assert_eq!(vec![(4, -2), (3, -2), (2, -2)], world.knots);
+        assert_eq!(vec![4, 3, 2], stats.distance);
+        assert_eq!(
+            vec![5, 4, 3],
+            stats.visited.iter().map(HashSet::len).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            "knot\tcells\tdistance\nH\t5\t4\n1\t4\t3\n2\t3\t2\n",
+            stats.report()
+        );
+
+        let csv = String::from_utf8(csv.finish().unwrap()).unwrap();
+        let rows: Vec<_> = csv.lines().collect();
+        assert_eq!(
+            1 + 5 * 3,
+            rows.len(),
+            "a header, then three knots for five states"
+        );
+        assert_eq!(
+            ["step,knot,x,y", "0,0,0,0", "0,1,0,0", "0,2,0,0", "1,0,1,0"],
+            rows[..5]
+        );
+        assert_eq!(["4,0,4,-2", "4,1,3,-2", "4,2,2,-2"], rows[13..]);
+    }
 }
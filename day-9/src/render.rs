@@ -0,0 +1,200 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use crate::{Coords, Observer, World, ORIGIN};
+
+/// How many trees across and down a frame shows.
+pub const VIEW_WIDTH: i64 = 48;
+pub const VIEW_HEIGHT: i64 = 24;
+
+/// Pixels per tree in PPM frames.
+const SCALE: usize = 8;
+
+/// A window onto the world that follows the head around.
+///
+/// The camera only moves when the head gets within a quarter of the frame of an edge, so the rope
+/// doesn't sit perfectly still in the middle while the world scrolls past.
+#[derive(Debug, Clone)]
+pub struct Camera {
+    width: i64,
+    height: i64,
+    top_left: Coords,
+}
+
+impl Camera {
+    /// A camera looking at the start.
+    pub fn new(width: i64, height: i64) -> Self {
+        assert!(width > 0 && height > 0);
+        Camera {
+            width,
+            height,
+            top_left: (ORIGIN.0 - width / 2, ORIGIN.1 - height / 2),
+        }
+    }
+
+    pub fn follow(&mut self, (x, y): Coords) {
+        let (left, top) = &mut self.top_left;
+        *left = keep_in_view(*left, self.width, x);
+        *top = keep_in_view(*top, self.height, y);
+    }
+
+    fn rows(&self) -> impl Iterator<Item = impl Iterator<Item = Coords>> {
+        let (left, top) = self.top_left;
+        let width = self.width;
+        (top..top + self.height).map(move |y| (left..left + width).map(move |x| (x, y)))
+    }
+}
+
+/// Shows the rope in the terminal, and/or saves it as numbered frames, after every step.
+#[derive(Debug)]
+pub struct Playback {
+    /// Frames per second in the terminal.
+    animate: Option<f64>,
+    frames: Option<PathBuf>,
+    camera: Camera,
+    trail: HashSet<Coords>,
+}
+
+impl Playback {
+    pub fn new(animate: Option<f64>, frames: Option<PathBuf>) -> Self {
+        if let Some(dir) = &frames {
+            fs::create_dir_all(dir).expect("create the frames directory");
+        }
+        Playback {
+            animate,
+            frames,
+            camera: Camera::new(VIEW_WIDTH, VIEW_HEIGHT),
+            trail: HashSet::new(),
+        }
+    }
+}
+
+impl Observer for Playback {
+    fn observe(&mut self, world: &World) {
+        self.camera.follow(world.head());
+        self.trail.insert(world.tail());
+        let step = world.steps();
+
+        if let Some(fps) = self.animate {
+            let mut stdout = io::stdout().lock();
+            // Back to the top left, and clear the screen.
+            write!(stdout, "\x1b[H\x1b[2J").unwrap();
+            write!(stdout, "{}", text(world, &self.trail, &self.camera)).unwrap();
+            writeln!(stdout, "step {step}, head at {:?}", world.head()).unwrap();
+            stdout.flush().unwrap();
+            thread::sleep(Duration::from_secs_f64(1.0 / fps));
+        }
+        if let Some(dir) = &self.frames {
+            let path = dir.join(format!("frame-{step:06}.ppm"));
+            fs::write(path, ppm(world, &self.trail, &self.camera)).unwrap();
+        }
+    }
+}
+
+/// Where one edge of the camera should be so that `position` is at least a margin away from it.
+fn keep_in_view(start: i64, length: i64, position: i64) -> i64 {
+    let margin = length / 4;
+    let first = start + margin;
+    let last = start + length - 1 - margin;
+    if position < first {
+        position - margin
+    } else if position > last {
+        position - (length - 1 - margin)
+    } else {
+        start
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    Head,
+    Knot(usize),
+    Start,
+    Trail,
+    Empty,
+}
+
+/// Knots go on top of the start, which goes on top of the trail.
+fn cell(world: &World, trail: &HashSet<Coords>, position: Coords) -> Cell {
+    if position == world.head() {
+        Cell::Head
+    } else if let Some(i) = world.find_knot(position) {
+        Cell::Knot(i)
+    } else if position == ORIGIN {
+        Cell::Start
+    } else if trail.contains(&position) {
+        Cell::Trail
+    } else {
+        Cell::Empty
+    }
+}
+
+/// The frame like in the puzzle description, with the trail drawn in as `#`.
+///
+/// ```text
+/// ......
+/// s##1H.
+/// ......
+/// ```
+pub fn text(world: &World, trail: &HashSet<Coords>, camera: &Camera) -> String {
+    let mut output = String::new();
+    for row in camera.rows() {
+        for position in row {
+            output.push(match cell(world, trail, position) {
+                Cell::Head => 'H',
+                // Knots past 9 carry on with letters.
+                Cell::Knot(i) => char::from_digit(i as u32, 36).unwrap_or('*'),
+                Cell::Start => 's',
+                Cell::Trail => '#',
+                Cell::Empty => '.',
+            });
+        }
+        output.push('\n');
+    }
+    output
+}
+
+/// The frame as a binary PPM, with each tree as a square of pixels.
+pub fn ppm(world: &World, trail: &HashSet<Coords>, camera: &Camera) -> Vec<u8> {
+    let width = camera.width as usize * SCALE;
+    let height = camera.height as usize * SCALE;
+    let mut output = format!("P6\n{width} {height}\n255\n").into_bytes();
+    output.reserve(width * height * 3);
+
+    for row in camera.rows() {
+        let colours: Vec<[u8; 3]> = row
+            .map(|position| colour(cell(world, trail, position), world.knots.len()))
+            .collect();
+        for _ in 0..SCALE {
+            for colour in &colours {
+                for _ in 0..SCALE {
+                    output.extend_from_slice(colour);
+                }
+            }
+        }
+    }
+
+    output
+}
+
+fn colour(cell: Cell, n_knots: usize) -> [u8; 3] {
+    match cell {
+        Cell::Head => [220, 50, 47],
+        // Fade from orange behind the head to yellow at the tail.
+        Cell::Knot(i) => {
+            let t = i as f64 / (n_knots.max(2) - 1) as f64;
+            [
+                (203.0 + 30.0 * t) as u8,
+                (75.0 + 110.0 * t) as u8,
+                (22.0 + 20.0 * t) as u8,
+            ]
+        }
+        Cell::Start => [38, 139, 210],
+        Cell::Trail => [88, 110, 117],
+        Cell::Empty => [0, 43, 54],
+    }
+}
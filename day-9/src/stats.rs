@@ -0,0 +1,100 @@
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+use crate::{Coords, Observer, World};
+
+/// Where every knot has been, and how far it went to get there.
+#[derive(Debug, Default)]
+pub struct KnotStats {
+    /// Positions each knot has been in, head first.
+    pub visited: Vec<HashSet<Coords>>,
+    /// How far each knot has moved, in steps of one tree (diagonals included).
+    pub distance: Vec<u64>,
+    last_seen: Vec<Coords>,
+}
+
+impl KnotStats {
+    pub fn new() -> Self {
+        KnotStats::default()
+    }
+
+    /// A table with a row per knot.
+    pub fn report(&self) -> String {
+        let mut output = String::from("knot\tcells\tdistance\n");
+        for (knot, (visited, distance)) in self.visited.iter().zip(&self.distance).enumerate() {
+            let name = if knot == 0 {
+                "H".to_owned()
+            } else {
+                knot.to_string()
+            };
+            writeln!(output, "{name}\t{}\t{distance}", visited.len()).unwrap();
+        }
+        output
+    }
+}
+
+impl Observer for KnotStats {
+    fn observe(&mut self, world: &World) {
+        if self.last_seen.is_empty() {
+            self.visited = vec![HashSet::new(); world.knots.len()];
+            self.distance = vec![0; world.knots.len()];
+            self.last_seen = world.knots.clone();
+        }
+
+        for (knot, &(x, y)) in world.knots.iter().enumerate() {
+            let (last_x, last_y) = self.last_seen[knot];
+            self.distance[knot] += x.abs_diff(last_x).max(y.abs_diff(last_y));
+            self.last_seen[knot] = (x, y);
+            self.visited[knot].insert((x, y));
+        }
+    }
+}
+
+/// Writes every knot's position after every step as CSV:
+///
+/// ```text
+/// step,knot,x,y
+/// 0,0,0,0
+/// 0,1,0,0
+/// 1,0,1,0
+/// ```
+///
+/// Step 0 is where the rope starts, and knot 0 is the head.
+#[derive(Debug)]
+pub struct TrailCsv<W: Write> {
+    output: W,
+    /// The first error, if writing failed; nothing more is written after that.
+    pub error: Option<io::Error>,
+}
+
+impl<W: Write> TrailCsv<W> {
+    pub fn new(mut output: W) -> Self {
+        let error = writeln!(output, "step,knot,x,y").err();
+        TrailCsv { output, error }
+    }
+
+    /// Flushes what's left, and reports whether everything was written.
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        self.output.flush()?;
+        Ok(self.output)
+    }
+}
+
+impl<W: Write> Observer for TrailCsv<W> {
+    fn observe(&mut self, world: &World) {
+        if self.error.is_some() {
+            return;
+        }
+        let step = world.steps();
+        for (knot, (x, y)) in world.knots.iter().enumerate() {
+            if let Err(error) = writeln!(self.output, "{step},{knot},{x},{y}") {
+                self.error = Some(error);
+                return;
+            }
+        }
+    }
+}